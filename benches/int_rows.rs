@@ -0,0 +1,58 @@
+// Compares writing and reading all-integer rows through the tagged `write`
+// and `read` path against `write_ints` and `read_ints`. Run with
+// `cargo bench --bench int_rows`.
+use simplified_storage::page::{DataField, Page};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const PAGE_SIZE: usize = 8192;
+const FIELDS: u16 = 8;
+const ROUNDS: u32 = 2000;
+
+fn time(f: impl Fn()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn main() {
+    let ints: Vec<u16> = (0..FIELDS).collect();
+    let fields: Vec<DataField> = ints.iter().copied().map(DataField::Integer).collect();
+
+    let tagged_write = time(|| {
+        let page = Page::new(0, PAGE_SIZE, None);
+        while page.write(&fields).is_ok() {}
+        black_box(page);
+    });
+    let packed_write = time(|| {
+        let page = Page::new(0, PAGE_SIZE, None);
+        while page.write_ints(&ints).is_ok() {}
+        black_box(page);
+    });
+
+    let tagged = Page::new(0, PAGE_SIZE, None);
+    while tagged.write(&fields).is_ok() {}
+    let packed = Page::new(0, PAGE_SIZE, None);
+    while packed.write_ints(&ints).is_ok() {}
+    let tagged_read = time(|| {
+        black_box(tagged.read().unwrap());
+    });
+    let packed_read = time(|| {
+        black_box(packed.read_ints().unwrap());
+    });
+
+    // Packed rows are smaller, so a page holds more of them; compare per row.
+    let per_row = |duration: Duration, page: &Page| duration / page.count() as u32;
+    println!(
+        "write: tagged {:?}/row, packed {:?}/row",
+        per_row(tagged_write, &tagged),
+        per_row(packed_write, &packed)
+    );
+    println!(
+        "read:  tagged {:?}/row, packed {:?}/row",
+        per_row(tagged_read, &tagged),
+        per_row(packed_read, &packed)
+    );
+}
@@ -0,0 +1,58 @@
+// A bloom filter stored as a plain bit array, so it can live inside a page
+// buffer. Each key sets `NUM_HASHES` bits chosen by double hashing the two
+// halves of its 64-bit FNV-1a hash.
+const NUM_HASHES: u64 = 3;
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+fn bit_indices(num_bytes: usize, key: &[u8]) -> impl Iterator<Item = usize> {
+    let hash = fnv1a(key);
+    let (first, second) = (hash & 0xFFFF_FFFF, (hash >> 32) | 1);
+    let num_bits = num_bytes as u64 * 8;
+    (0..NUM_HASHES).map(move |i| (first.wrapping_add(i.wrapping_mul(second)) % num_bits) as usize)
+}
+
+/// Does nothing when `bits` is empty, since there is no bit to set.
+pub fn insert(bits: &mut [u8], key: &[u8]) {
+    if bits.is_empty() {
+        return;
+    }
+    for index in bit_indices(bits.len(), key) {
+        bits[index / 8] |= 1 << (index % 8);
+    }
+}
+
+/// Whether `key` may have been inserted. `false` is always correct; `true`
+/// can be a false positive.
+pub fn might_contain(bits: &[u8], key: &[u8]) -> bool {
+    bits.is_empty()
+        || bit_indices(bits.len(), key).all(|index| bits[index / 8] & (1 << (index % 8)) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives() {
+        let mut bits = vec![0u8; 64];
+        for key in 0..40u32 {
+            insert(&mut bits, &key.to_le_bytes());
+        }
+        assert!((0..40u32).all(|key| might_contain(&bits, &key.to_le_bytes())));
+        assert!((1000..1100u32).any(|key| !might_contain(&bits, &key.to_le_bytes())));
+    }
+
+    #[test]
+    fn test_empty_filter_contains_everything() {
+        insert(&mut [], b"key");
+        assert!(might_contain(&[], b"key"));
+    }
+}
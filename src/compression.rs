@@ -0,0 +1,116 @@
+// A small LZ77 codec. The output is a sequence of tokens, each starting with
+// a control byte:
+//   0xxxxxxx  a literal run of x + 1 bytes follows
+//   1xxxxxxx  copy x + MIN_MATCH bytes from `distance` bytes back, where
+//             `distance` follows as a little-endian u16
+const MIN_MATCH: usize = 4;
+const MAX_MATCH: usize = 0x7F + MIN_MATCH;
+const MAX_LITERALS: usize = 0x80;
+const MAX_DISTANCE: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 12;
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+    (value.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+fn flush_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(MAX_LITERALS) {
+        output.push((chunk.len() - 1) as u8);
+        output.extend_from_slice(chunk);
+    }
+}
+
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    // Most recent position of each hashed 4-byte sequence, offset by one so
+    // that 0 means empty.
+    let mut table = vec![0usize; 1 << HASH_BITS];
+    let mut literal_start = 0;
+    let mut position = 0;
+    while position + MIN_MATCH <= input.len() {
+        let bucket = hash(&input[position..]);
+        let candidate = table[bucket].checked_sub(1);
+        table[bucket] = position + 1;
+        let match_len = candidate
+            .filter(|&candidate| position - candidate <= MAX_DISTANCE)
+            .map(|candidate| {
+                input[candidate..]
+                    .iter()
+                    .zip(&input[position..])
+                    .take(MAX_MATCH)
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            })
+            .unwrap_or(0);
+        if match_len < MIN_MATCH {
+            position += 1;
+            continue;
+        }
+        flush_literals(&mut output, &input[literal_start..position]);
+        let distance = position - candidate.unwrap();
+        output.push(0x80 | (match_len - MIN_MATCH) as u8);
+        output.extend_from_slice(&(distance as u16).to_le_bytes());
+        position += match_len;
+        literal_start = position;
+    }
+    flush_literals(&mut output, &input[literal_start..]);
+    output
+}
+
+/// Reverses `compress`, or returns `None` if the input is truncated or
+/// refers back past the start of the output.
+pub fn decompress(input: &[u8]) -> Option<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut position = 0;
+    while let Some(&control) = input.get(position) {
+        position += 1;
+        if control & 0x80 == 0 {
+            let len = control as usize + 1;
+            output.extend_from_slice(input.get(position..position + len)?);
+            position += len;
+        } else {
+            let len = (control & 0x7F) as usize + MIN_MATCH;
+            let distance =
+                u16::from_le_bytes(input.get(position..position + 2)?.try_into().unwrap());
+            position += 2;
+            if distance == 0 {
+                return None;
+            }
+            let start = output.len().checked_sub(distance as usize)?;
+            // Copies byte by byte since a match may overlap its own output.
+            for i in 0..len {
+                output.push(output[start + i]);
+            }
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let inputs: [&[u8]; 4] = [
+            b"",
+            b"abc",
+            &[b'a'; 300],
+            b"the quick brown fox jumps over the lazy dog, the quick brown fox jumps again",
+        ];
+        for input in inputs {
+            assert_eq!(decompress(&compress(input)).unwrap(), input);
+        }
+        let noise: Vec<u8> = (0..1000u32)
+            .map(|i| (i.wrapping_mul(7919) >> 3) as u8)
+            .collect();
+        assert_eq!(decompress(&compress(&noise)).unwrap(), noise);
+    }
+
+    #[test]
+    fn test_decompress_rejects_bad_distance() {
+        assert_eq!(decompress(&[0x80, 5, 0]), None);
+        assert_eq!(decompress(&[0x03, b'a']), None);
+    }
+}
@@ -0,0 +1,47 @@
+use crate::page::DataField;
+
+/// Renders rows as RFC 4180 CSV, one CRLF-terminated line per row. Fields
+/// that contain a comma, quote or line break are quoted, with embedded quotes
+/// doubled.
+pub fn rows_to_csv(rows: &[Vec<DataField>]) -> String {
+    let mut csv = String::new();
+    for row in rows {
+        let line: Vec<String> = row.iter().map(|field| escape(&field.to_string())).collect();
+        csv.push_str(&line.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+fn escape(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_to_csv_escapes_text() {
+        let rows = vec![
+            vec![
+                DataField::Integer(1),
+                DataField::Text("hello, \"world\"".to_string()),
+                DataField::Null,
+            ],
+            vec![
+                DataField::Integer(2),
+                DataField::Text("plain".to_string()),
+                DataField::Bool(true),
+            ],
+        ];
+        assert_eq!(
+            rows_to_csv(&rows),
+            "1,\"hello, \"\"world\"\"\",\r\n2,plain,true\r\n"
+        );
+    }
+}
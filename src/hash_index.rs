@@ -0,0 +1,74 @@
+use crate::page::{DataField, Endianness, Page, PageError};
+use std::collections::HashMap;
+
+/// Maps the values of one field to the slots of the rows holding them.
+///
+/// Values are keyed by their encoded bytes rather than by `DataField`
+/// itself, since `Float64` has no total equality. Two floats therefore match
+/// only when their bits match, so `0.0` and `-0.0` are distinct keys.
+pub struct HashIndex {
+    field_index: usize,
+    slots: HashMap<Vec<u8>, Vec<usize>>,
+}
+
+impl HashIndex {
+    /// Indexes field `field_index` of every live row in `page`. Rows with
+    /// fewer fields are left out of the index.
+    pub fn build(page: &Page, field_index: usize) -> Result<Self, PageError> {
+        let mut index = Self {
+            field_index,
+            slots: HashMap::new(),
+        };
+        for slot in 0..page.slot_count() {
+            let row = match page.get_row(slot) {
+                Ok(row) => row,
+                Err(PageError::InvalidSlot(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            if let Some(value) = row.get(field_index) {
+                index.slots.entry(Self::key(value)?).or_default().push(slot);
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn field_index(&self) -> usize {
+        self.field_index
+    }
+
+    /// Slots whose indexed field equals `value`, in ascending order.
+    pub fn lookup(&self, value: &DataField) -> Vec<usize> {
+        Self::key(value)
+            .ok()
+            .and_then(|key| self.slots.get(&key))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn key(value: &DataField) -> Result<Vec<u8>, PageError> {
+        Page::encode_row(std::slice::from_ref(value), Endianness::Little)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_returns_matching_slots() {
+        let page = Page::new(0, 8192, None);
+        for i in 0..10 {
+            page.write(&[DataField::Integer(i % 3), DataField::Integer(i)])
+                .unwrap();
+        }
+        page.write(&[DataField::Text("a".to_string())]).unwrap();
+        page.delete(3).unwrap();
+        let index = HashIndex::build(&page, 0).unwrap();
+        assert_eq!(index.lookup(&DataField::Integer(0)), vec![0, 6, 9]);
+        assert_eq!(index.lookup(&DataField::Integer(1)), vec![1, 4, 7]);
+        assert_eq!(index.lookup(&DataField::Text("a".to_string())), vec![10]);
+        assert_eq!(index.lookup(&DataField::Integer(5)), Vec::<usize>::new());
+        // Same number, different type.
+        assert_eq!(index.lookup(&DataField::Int32(1)), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,742 @@
+use crate::page::DataField;
+use std::fmt;
+
+// Rows are written as a JSON array of arrays. This is a hand-written encoder
+// and parser rather than serde, which the crate does not depend on.
+//
+// `Integer` becomes a number, `Text` a string, `Null` null, `Bool` a boolean
+// and `Array` a nested array. Every other type is written as an object with
+// one member named after the variant, so it reads back as the same variant:
+// `{"Int32":-5}`, `{"Float64":2.0}`, `{"Bytes":"00ff"}`, `{"Json":{...}}`.
+// Integers are written and parsed exactly, without going through `f64`.
+// Non-finite floats are written as the strings "NaN", "inf" and "-inf".
+// `Decimal` is `[mantissa,scale]` and `Overflow` is
+// `[datatype_num,page_id,len]`.
+//
+// Untagged numbers that are not a u16 are parsed as `Int64` when whole and
+// as `Float64` otherwise, so hand-written JSON still reads.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    pub offset: usize,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid json at byte {}", self.offset)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// Nesting of arrays and objects deeper than this is rejected by the parsers,
+/// so hostile input cannot overflow the stack.
+pub const MAX_DEPTH: usize = 128;
+
+/// A JSON document, standing in for `serde_json::Value` since the crate has
+/// no dependencies. Object members keep the order they were parsed or built
+/// in, and numbers are held as `f64`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    pub fn parse(json: &str) -> Result<JsonValue, JsonError> {
+        let mut parser = Parser {
+            bytes: json.as_bytes(),
+            offset: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.offset != parser.bytes.len() {
+            return Err(parser.error());
+        }
+        Ok(value)
+    }
+
+    /// How deeply arrays and objects nest, 0 for a scalar.
+    pub fn depth(&self) -> usize {
+        let children = match self {
+            JsonValue::Array(elements) => elements.iter().collect::<Vec<_>>(),
+            JsonValue::Object(members) => members.iter().map(|(_, member)| member).collect(),
+            _ => return 0,
+        };
+        1 + children
+            .iter()
+            .map(|child| child.depth())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Writes the compact form, with no whitespace. Non-finite numbers are
+/// written as null.
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut json = String::new();
+        write_value(&mut json, self);
+        f.write_str(&json)
+    }
+}
+
+fn write_value(json: &mut String, value: &JsonValue) {
+    match value {
+        JsonValue::Null => json.push_str("null"),
+        JsonValue::Bool(boolean) => json.push_str(&boolean.to_string()),
+        JsonValue::Number(number) if !number.is_finite() => json.push_str("null"),
+        JsonValue::Number(number) => json.push_str(&number.to_string()),
+        JsonValue::String(text) => write_string(json, text),
+        JsonValue::Array(elements) => {
+            json.push('[');
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_value(json, element);
+            }
+            json.push(']');
+        }
+        JsonValue::Object(members) => {
+            json.push('{');
+            for (i, (key, member)) in members.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_string(json, key);
+                json.push(':');
+                write_value(json, member);
+            }
+            json.push('}');
+        }
+    }
+}
+
+pub fn rows_to_json(rows: &[Vec<DataField>]) -> String {
+    let mut json = String::from("[");
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        write_array(&mut json, row);
+    }
+    json.push(']');
+    json
+}
+
+fn write_array(json: &mut String, fields: &[DataField]) {
+    json.push('[');
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        write_field(json, field);
+    }
+    json.push(']');
+}
+
+fn write_field(json: &mut String, field: &DataField) {
+    match field {
+        DataField::Integer(int) => json.push_str(&int.to_string()),
+        DataField::Text(text) => write_string(json, text),
+        DataField::Null => json.push_str("null"),
+        DataField::Bool(boolean) => json.push_str(&boolean.to_string()),
+        DataField::Array(elements) => write_array(json, elements),
+        _ => {
+            json.push('{');
+            write_string(json, variant_name(field));
+            json.push(':');
+            write_payload(json, field);
+            json.push('}');
+        }
+    }
+}
+
+fn variant_name(field: &DataField) -> &'static str {
+    match field {
+        DataField::Text(_) => "Text",
+        DataField::Integer(_) => "Integer",
+        DataField::Int32(_) => "Int32",
+        DataField::Int64(_) => "Int64",
+        DataField::Float64(_) => "Float64",
+        DataField::Null => "Null",
+        DataField::Bool(_) => "Bool",
+        DataField::Bytes(_) => "Bytes",
+        DataField::LargeText(_) => "LargeText",
+        DataField::UInt32(_) => "UInt32",
+        DataField::Timestamp(_) => "Timestamp",
+        DataField::Uuid(_) => "Uuid",
+        DataField::VarInt(_) => "VarInt",
+        DataField::Overflow { .. } => "Overflow",
+        DataField::Decimal { .. } => "Decimal",
+        DataField::Char(_) => "Char",
+        DataField::Array(_) => "Array",
+        DataField::CheckedBytes(_) => "CheckedBytes",
+        DataField::Json(_) => "Json",
+        DataField::Int16(_) => "Int16",
+    }
+}
+
+fn write_payload(json: &mut String, field: &DataField) {
+    match field {
+        DataField::LargeText(text) => write_string(json, text),
+        DataField::Bytes(_)
+        | DataField::CheckedBytes(_)
+        | DataField::Uuid(_)
+        | DataField::Char(_) => write_string(json, &field.to_string()),
+        DataField::Json(value) => write_value(json, value),
+        DataField::Float64(float) if !float.is_finite() => write_string(json, &float.to_string()),
+        DataField::Decimal { mantissa, scale } => {
+            json.push_str(&format!("[{},{}]", mantissa, scale))
+        }
+        DataField::Overflow {
+            datatype_num,
+            page_id,
+            len,
+        } => json.push_str(&format!("[{},{},{}]", datatype_num, page_id, len)),
+        // `f64`'s Display never uses an exponent, so it is always a valid
+        // number, and the integer types print exactly.
+        _ => json.push_str(&field.to_string()),
+    }
+}
+
+fn write_string(json: &mut String, text: &str) {
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+pub fn rows_from_json(json: &str) -> Result<Vec<Vec<DataField>>, JsonError> {
+    let mut parser = Parser {
+        bytes: json.as_bytes(),
+        offset: 0,
+        depth: 0,
+    };
+    let rows = parser.array(|parser| parser.array(Parser::field))?;
+    parser.skip_whitespace();
+    if parser.offset != parser.bytes.len() {
+        return Err(parser.error());
+    }
+    Ok(rows)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self) -> JsonError {
+        JsonError {
+            offset: self.offset,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.offset) {
+            self.offset += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.offset).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        if self.peek() != Some(byte) {
+            return Err(self.error());
+        }
+        self.offset += 1;
+        Ok(())
+    }
+
+    fn array<T>(
+        &mut self,
+        element: impl FnMut(&mut Self) -> Result<T, JsonError>,
+    ) -> Result<Vec<T>, JsonError> {
+        self.sequence(b'[', b']', element)
+    }
+
+    fn object(&mut self) -> Result<JsonValue, JsonError> {
+        let members = self.sequence(b'{', b'}', |parser| {
+            let key = parser.string()?;
+            parser.expect(b':')?;
+            Ok((key, parser.value()?))
+        })?;
+        Ok(JsonValue::Object(members))
+    }
+
+    // Parses `open`, comma-separated elements and then `close`.
+    fn sequence<T>(
+        &mut self,
+        open: u8,
+        close: u8,
+        mut element: impl FnMut(&mut Self) -> Result<T, JsonError>,
+    ) -> Result<Vec<T>, JsonError> {
+        if self.peek() == Some(open) && self.depth == MAX_DEPTH {
+            return Err(self.error());
+        }
+        self.expect(open)?;
+        self.depth += 1;
+        let mut elements = Vec::new();
+        if self.peek() == Some(close) {
+            self.offset += 1;
+            self.depth -= 1;
+            return Ok(elements);
+        }
+        loop {
+            elements.push(element(self)?);
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(byte) if byte == close => {
+                    self.offset += 1;
+                    self.depth -= 1;
+                    return Ok(elements);
+                }
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn field(&mut self) -> Result<DataField, JsonError> {
+        match self.peek() {
+            Some(b'"') => Ok(DataField::Text(self.string()?)),
+            Some(b'[') => Ok(DataField::Array(self.array(Parser::field)?)),
+            Some(b'{') => self.tagged_field(),
+            Some(b'n') => self.literal("null", DataField::Null),
+            Some(b't') => self.literal("true", DataField::Bool(true)),
+            Some(b'f') => self.literal("false", DataField::Bool(false)),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.error()),
+        }
+    }
+
+    // Parses a `{"Variant":payload}` field written by `write_field`.
+    fn tagged_field(&mut self) -> Result<DataField, JsonError> {
+        self.expect(b'{')?;
+        let start = self.offset;
+        let name = self.string()?;
+        self.expect(b':')?;
+        let field = match name.as_str() {
+            "Text" => DataField::Text(self.string()?),
+            "Integer" => DataField::Integer(self.int()?),
+            "Int32" => DataField::Int32(self.int()?),
+            "Int64" => DataField::Int64(self.int()?),
+            "Float64" => DataField::Float64(self.float()?),
+            "Null" => self.literal("null", DataField::Null)?,
+            "Bool" => match self.field()? {
+                DataField::Bool(boolean) => DataField::Bool(boolean),
+                _ => return Err(JsonError { offset: start }),
+            },
+            "Bytes" => DataField::Bytes(self.hex()?),
+            "LargeText" => DataField::LargeText(self.string()?),
+            "UInt32" => DataField::UInt32(self.int()?),
+            "Timestamp" => DataField::Timestamp(self.int()?),
+            "Uuid" => {
+                let offset = self.offset;
+                let bytes = self.hex()?;
+                DataField::Uuid(bytes.try_into().map_err(|_| JsonError { offset })?)
+            }
+            "VarInt" => DataField::VarInt(self.int()?),
+            "Overflow" => {
+                self.expect(b'[')?;
+                let datatype_num = self.int()?;
+                self.expect(b',')?;
+                let page_id = self.int()?;
+                self.expect(b',')?;
+                let len = self.int()?;
+                self.expect(b']')?;
+                DataField::Overflow {
+                    datatype_num,
+                    page_id,
+                    len,
+                }
+            }
+            "Decimal" => {
+                self.expect(b'[')?;
+                let mantissa = self.int()?;
+                self.expect(b',')?;
+                let scale = self.int()?;
+                self.expect(b']')?;
+                DataField::Decimal { mantissa, scale }
+            }
+            "Char" => {
+                let offset = self.offset;
+                let text = self.string()?;
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => DataField::Char(c),
+                    _ => return Err(JsonError { offset }),
+                }
+            }
+            "Array" => DataField::Array(self.array(Parser::field)?),
+            "CheckedBytes" => DataField::CheckedBytes(self.hex()?),
+            "Json" => DataField::Json(self.value()?),
+            "Int16" => DataField::Int16(self.int()?),
+            _ => return Err(JsonError { offset: start }),
+        };
+        self.expect(b'}')?;
+        Ok(field)
+    }
+
+    fn int<T: std::str::FromStr>(&mut self) -> Result<T, JsonError> {
+        self.skip_whitespace();
+        let (start, text) = self.number_text();
+        text.parse().map_err(|_| JsonError { offset: start })
+    }
+
+    // A number, or one of the strings `write_payload` uses for non-finite
+    // floats.
+    fn float(&mut self) -> Result<f64, JsonError> {
+        if self.peek() != Some(b'"') {
+            return self.int();
+        }
+        let start = self.offset;
+        match self.string()?.as_str() {
+            text @ ("NaN" | "inf" | "-inf") => Ok(text.parse().unwrap()),
+            _ => Err(JsonError { offset: start }),
+        }
+    }
+
+    // A string of hex digit pairs, ignoring the dashes of a `Uuid`.
+    fn hex(&mut self) -> Result<Vec<u8>, JsonError> {
+        let start = self.offset;
+        let digits: Vec<u8> = self.string()?.bytes().filter(|&b| b != b'-').collect();
+        if !digits.len().is_multiple_of(2) {
+            return Err(JsonError { offset: start });
+        }
+        digits
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or(JsonError { offset: start })
+            })
+            .collect()
+    }
+
+    fn value(&mut self) -> Result<JsonValue, JsonError> {
+        match self.peek() {
+            Some(b'"') => Ok(JsonValue::String(self.string()?)),
+            Some(b'[') => Ok(JsonValue::Array(self.array(Parser::value)?)),
+            Some(b'{') => self.object(),
+            Some(b'n') => self.literal("null", JsonValue::Null),
+            Some(b't') => self.literal("true", JsonValue::Bool(true)),
+            Some(b'f') => self.literal("false", JsonValue::Bool(false)),
+            Some(b'-' | b'0'..=b'9') => {
+                let (start, text) = self.number_text();
+                text.parse::<f64>()
+                    .map(JsonValue::Number)
+                    .map_err(|_| JsonError { offset: start })
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn literal<T>(&mut self, text: &str, value: T) -> Result<T, JsonError> {
+        if !self.bytes[self.offset..].starts_with(text.as_bytes()) {
+            return Err(self.error());
+        }
+        self.offset += text.len();
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<DataField, JsonError> {
+        let (start, text) = self.number_text();
+        if let Ok(int) = text.parse::<i64>() {
+            return Ok(match u16::try_from(int) {
+                Ok(int) => DataField::Integer(int),
+                Err(_) => DataField::Int64(int),
+            });
+        }
+        text.parse::<f64>()
+            .map(DataField::Float64)
+            .map_err(|_| JsonError { offset: start })
+    }
+
+    // Returns where the number starts and its text, leaving validation to
+    // the caller's `parse`.
+    fn number_text(&mut self) -> (usize, &str) {
+        let start = self.offset;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.bytes.get(self.offset)
+        {
+            self.offset += 1;
+        }
+        (
+            start,
+            std::str::from_utf8(&self.bytes[start..self.offset]).unwrap(),
+        )
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut text = String::new();
+        loop {
+            let start = self.offset;
+            while let Some(&byte) = self.bytes.get(self.offset) {
+                if byte == b'"' || byte == b'\\' {
+                    break;
+                }
+                self.offset += 1;
+            }
+            // The input is a `&str` and scanning only stops at ASCII bytes,
+            // so this slice is always valid UTF-8.
+            text.push_str(std::str::from_utf8(&self.bytes[start..self.offset]).unwrap());
+            match self.bytes.get(self.offset) {
+                Some(b'"') => {
+                    self.offset += 1;
+                    return Ok(text);
+                }
+                Some(b'\\') => {
+                    self.offset += 1;
+                    text.push(self.escape()?);
+                }
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, JsonError> {
+        let byte = *self.bytes.get(self.offset).ok_or(self.error())?;
+        self.offset += 1;
+        Ok(match byte {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let high = self.hex4()?;
+                let code = if (0xD800..0xDC00).contains(&high) {
+                    if !self.bytes[self.offset..].starts_with(b"\\u") {
+                        return Err(self.error());
+                    }
+                    self.offset += 2;
+                    let low = self.hex4()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(self.error());
+                    }
+                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or(self.error())?
+            }
+            _ => return Err(self.error()),
+        })
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .bytes
+            .get(self.offset..self.offset + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or(self.error())?;
+        self.offset += 4;
+        Ok(digits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        let rows = vec![
+            vec![
+                DataField::Integer(1),
+                DataField::Text("say \"hi\"\n\u{e9}".to_string()),
+                DataField::Null,
+                DataField::Bool(false),
+                DataField::Float64(-2.5),
+                DataField::Int64(-70000),
+                DataField::Array(vec![DataField::Integer(2), DataField::Array(vec![])]),
+            ],
+            vec![],
+        ];
+        let json = rows_to_json(&rows);
+        assert_eq!(
+            json,
+            "[[1,\"say \\\"hi\\\"\\n\u{e9}\",null,false,{\"Float64\":-2.5},{\"Int64\":-70000},[2,[]]],[]]"
+        );
+        assert_eq!(rows_from_json(&json).unwrap(), rows);
+        assert_eq!(
+            rows_from_json(" [ [ \"\\ud83d\\ude00\" ] ] ").unwrap(),
+            vec![vec![DataField::Text("\u{1F600}".to_string())]]
+        );
+    }
+
+    #[test]
+    fn test_json_value_round_trip() {
+        let text = r#"{"name":"a\"b","tags":["x",null,true],"nested":{"n":-1.5,"e":{}}}"#;
+        let value = JsonValue::parse(text).unwrap();
+        assert_eq!(
+            value,
+            JsonValue::Object(vec![
+                ("name".to_string(), JsonValue::String("a\"b".to_string())),
+                (
+                    "tags".to_string(),
+                    JsonValue::Array(vec![
+                        JsonValue::String("x".to_string()),
+                        JsonValue::Null,
+                        JsonValue::Bool(true),
+                    ])
+                ),
+                (
+                    "nested".to_string(),
+                    JsonValue::Object(vec![
+                        ("n".to_string(), JsonValue::Number(-1.5)),
+                        ("e".to_string(), JsonValue::Object(vec![])),
+                    ])
+                ),
+            ])
+        );
+        assert_eq!(value.to_string(), text);
+        assert_eq!(
+            JsonValue::parse(" [ 1 , 2 ] ").unwrap().to_string(),
+            "[1,2]"
+        );
+        assert_eq!(JsonValue::parse("{\"a\" 1}"), Err(JsonError { offset: 5 }));
+        assert_eq!(JsonValue::parse("{} x"), Err(JsonError { offset: 3 }));
+    }
+
+    #[test]
+    fn test_rows_from_json_rejects_invalid() {
+        assert_eq!(rows_from_json("[[1,]]"), Err(JsonError { offset: 4 }));
+        assert_eq!(rows_from_json("[[1]] x"), Err(JsonError { offset: 6 }));
+        assert!(rows_from_json("[[\"open]]").is_err());
+    }
+
+    #[test]
+    fn test_json_round_trips_every_variant() {
+        let cases = vec![
+            (DataField::Text("a".to_string()), r#""a""#),
+            (DataField::Integer(7), "7"),
+            (DataField::Int32(2), r#"{"Int32":2}"#),
+            (
+                DataField::Int64(i64::MIN),
+                r#"{"Int64":-9223372036854775808}"#,
+            ),
+            (DataField::Float64(2.0), r#"{"Float64":2}"#),
+            (
+                DataField::Float64(f64::NEG_INFINITY),
+                r#"{"Float64":"-inf"}"#,
+            ),
+            (DataField::Null, "null"),
+            (DataField::Bool(true), "true"),
+            (DataField::Bytes(vec![0, 255]), r#"{"Bytes":"00ff"}"#),
+            (
+                DataField::LargeText("b".to_string()),
+                r#"{"LargeText":"b"}"#,
+            ),
+            (DataField::UInt32(3), r#"{"UInt32":3}"#),
+            (DataField::Timestamp(-4), r#"{"Timestamp":-4}"#),
+            (
+                DataField::Uuid([0xab; 16]),
+                r#"{"Uuid":"abababab-abab-abab-abab-abababababab"}"#,
+            ),
+            (
+                DataField::VarInt(u64::MAX),
+                r#"{"VarInt":18446744073709551615}"#,
+            ),
+            (
+                DataField::Overflow {
+                    datatype_num: 8,
+                    page_id: 3,
+                    len: 70000,
+                },
+                r#"{"Overflow":[8,3,70000]}"#,
+            ),
+            (
+                DataField::Decimal {
+                    mantissa: -1234,
+                    scale: 2,
+                },
+                r#"{"Decimal":[-1234,2]}"#,
+            ),
+            (DataField::Char('\u{e9}'), "{\"Char\":\"\u{e9}\"}"),
+            (
+                DataField::Array(vec![DataField::Int16(1), DataField::Null]),
+                r#"[{"Int16":1},null]"#,
+            ),
+            (DataField::CheckedBytes(vec![1]), r#"{"CheckedBytes":"01"}"#),
+            (
+                DataField::Json(JsonValue::Object(vec![(
+                    "n".to_string(),
+                    JsonValue::Number(1.0),
+                )])),
+                r#"{"Json":{"n":1}}"#,
+            ),
+            (DataField::Int16(-2), r#"{"Int16":-2}"#),
+        ];
+        for (field, expected) in cases {
+            let rows = vec![vec![field]];
+            let json = rows_to_json(&rows);
+            assert_eq!(json, format!("[[{}]]", expected));
+            assert_eq!(rows_from_json(&json).unwrap(), rows);
+        }
+        let nan = rows_from_json(r#"[[{"Float64":"NaN"}]]"#).unwrap();
+        assert!(matches!(nan[0][0], DataField::Float64(float) if float.is_nan()));
+        assert_eq!(
+            rows_from_json(r#"[[{"Integer":1},{"Text":"t"}]]"#).unwrap(),
+            vec![vec![
+                DataField::Integer(1),
+                DataField::Text("t".to_string())
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_rows_from_json_rejects_bad_tags() {
+        assert_eq!(
+            rows_from_json(r#"[[{"Nope":1}]]"#),
+            Err(JsonError { offset: 3 })
+        );
+        assert_eq!(
+            rows_from_json(r#"[[{"Int16":70000}]]"#),
+            Err(JsonError { offset: 11 })
+        );
+        assert!(rows_from_json(r#"[[{"Uuid":"00ff"}]]"#).is_err());
+        assert!(rows_from_json(r#"[[{"Char":"ab"}]]"#).is_err());
+        assert!(rows_from_json(r#"[[{"Int32":1,"Int64":2}]]"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_deep_nesting() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert_eq!(
+            JsonValue::parse(&nested(MAX_DEPTH)).unwrap().depth(),
+            MAX_DEPTH
+        );
+        assert_eq!(
+            JsonValue::parse(&nested(MAX_DEPTH + 1)),
+            Err(JsonError { offset: MAX_DEPTH })
+        );
+        assert!(JsonValue::parse(&nested(1_000_000)).is_err());
+        assert!(rows_from_json(&format!("[{}]", nested(1_000_000))).is_err());
+    }
+}
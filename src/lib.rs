@@ -1,41 +1,2253 @@
-mod page;
-mod page_scanner;
-use crate::page::{DataField, Page};
-use std::fs::{File, OpenOptions};
-use std::io::{Read, Result, Seek, Write};
+pub mod bloom;
+pub mod checksum;
+pub mod compression;
+pub mod csv;
+pub mod hash_index;
+pub mod json;
+pub mod page;
+pub mod page_cache;
+pub mod page_scanner;
+pub mod record;
+pub mod schema;
+pub mod wal;
+use crate::page::PageError;
+use crate::page::{push_distinct, row_size, DataField, Page, PageView, TextEncoding};
+use crate::page_cache::PageCache;
+use crate::wal::WriteAheadLog;
+use std::fmt;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::Path;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    Page(PageError),
+    PageSizeTooLarge(usize),
+    PageNotFound(u16),
+    NoFile,
+    BrokenOverflowChain(u16),
+    Locked,
+    TruncatedFile { expected: u64, found: u64 },
+    CompressedFile,
+    RecordTooLong { len: usize, max_len: usize },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(error) => write!(f, "io error: {}", error),
+            StorageError::Page(error) => write!(f, "page error: {}", error),
+            StorageError::PageSizeTooLarge(page_size) => {
+                write!(f, "page size {} exceeds {}", page_size, u16::MAX)
+            }
+            StorageError::PageNotFound(page_id) => write!(f, "page {} does not exist", page_id),
+            StorageError::NoFile => write!(f, "storage is not backed by a file"),
+            StorageError::BrokenOverflowChain(page_id) => {
+                write!(f, "overflow chain breaks at page {}", page_id)
+            }
+            StorageError::Locked => write!(f, "storage file is locked by another writer"),
+            StorageError::TruncatedFile { expected, found } => write!(
+                f,
+                "file ends mid-page: expected {} bytes but found {}",
+                expected, found
+            ),
+            StorageError::CompressedFile => {
+                write!(f, "compressed files can only be read with Storage::open")
+            }
+            StorageError::RecordTooLong { len, max_len } => {
+                write!(
+                    f,
+                    "record of {} bytes exceeds the limit of {}",
+                    len, max_len
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io(error) => Some(error),
+            StorageError::Page(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(error: io::Error) -> Self {
+        StorageError::Io(error)
+    }
+}
+
+impl From<PageError> for StorageError {
+    fn from(error: PageError) -> Self {
+        StorageError::Page(error)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+/// Size in bytes of the pages of a storage file, unless a different size is
+/// passed to one of the `_with_page_size` functions.
+pub const PAGE_SIZE: usize = 8192;
+
+/// How `Storage::open_with_options` opens a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageOptions {
+    pub page_size: usize,
+    /// Whether pages have their checksum verified as they are loaded.
+    pub verify_on_read: bool,
+    /// Load pages on demand, keeping at most this many in memory, as
+    /// `open_cached` does.
+    pub cache_capacity: Option<usize>,
+}
+
+impl Default for StorageOptions {
+    fn default() -> Self {
+        StorageOptions {
+            page_size: PAGE_SIZE,
+            verify_on_read: true,
+            cache_capacity: None,
+        }
+    }
+}
+
+/// Callback given a page id and an amount: bytes for writes, rows for reads.
+type Hook = Box<dyn Fn(u16, usize) + Send + Sync>;
+
+/// Physical location of a row: the page it is on and its slot there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RowId {
+    pub page_id: u16,
+    pub slot: u16,
+}
 
 pub struct Storage {
     pages: RwLock<Vec<Arc<Page>>>,
+    page_size: usize,
+    file: Option<Mutex<File>>,
+    cache: Option<Mutex<PageCache>>,
+    on_write: Option<Hook>,
+    on_read: Option<Hook>,
+    text_encoding: TextEncoding,
+    verify_on_read: bool,
+    compressed: bool,
 }
 
 impl Storage {
-    pub fn insert_data(file_path: &str, data_fields: &Vec<DataField>) -> Result<()> {
-        let path = Path::new(file_path);
-        let page_size = 8192;
-        let (mut file, page) = if path.exists() {
-            let mut file = OpenOptions::new().read(true).write(true).open(file_path)?;
-            let mut buffer = vec![0u8; page_size];
-            file.read_exact(&mut buffer)?;
-            let page = Page::new(page_size, Some(buffer));
-            (file, page)
+    /// Creates an empty storage whose pages are `page_size` bytes long.
+    ///
+    /// Page headers store offsets as `u16`, so `page_size` must not exceed
+    /// `u16::MAX`; larger sizes are rejected with `PageSizeTooLarge`.
+    pub fn with_page_size(page_size: usize) -> Result<Storage> {
+        Self::check_page_size(page_size)?;
+        Ok(Storage {
+            pages: RwLock::new(Vec::new()),
+            page_size,
+            file: None,
+            cache: None,
+            on_write: None,
+            on_read: None,
+            text_encoding: TextEncoding::default(),
+            verify_on_read: true,
+            compressed: false,
+        })
+    }
+
+    fn check_page_size(page_size: usize) -> Result<()> {
+        if page_size > u16::MAX as usize {
+            return Err(StorageError::PageSizeTooLarge(page_size));
+        }
+        Ok(())
+    }
+
+    /// Loads every page of the file into memory. The storage keeps the file
+    /// open, and `insert` writes each modified page back to it. Rows left in
+    /// the write-ahead log by an interrupted `insert_data_logged` are applied
+    /// first.
+    ///
+    /// A file written with compression on is read back compressed, and the
+    /// storage keeps compression on for it.
+    pub fn open(file_path: &str) -> Result<Storage> {
+        Self::open_with_page_size(file_path, PAGE_SIZE)
+    }
+
+    /// Like `open`, for a file flushed by a storage made with
+    /// `with_page_size(page_size)`. The file does not record its page size,
+    /// so it must be opened with the size it was written with.
+    pub fn open_with_page_size(file_path: &str, page_size: usize) -> Result<Storage> {
+        Self::open_with_options(
+            file_path,
+            &StorageOptions {
+                page_size,
+                ..StorageOptions::default()
+            },
+        )
+    }
+
+    /// Opens the file as `open` does, or as `open_cached` does when
+    /// `options.cache_capacity` is set, with the page size and checksum
+    /// verification `options` asks for.
+    pub fn open_with_options(file_path: &str, options: &StorageOptions) -> Result<Storage> {
+        Self::recover(file_path, options.page_size)?;
+        let mut storage = Storage::with_page_size(options.page_size)?;
+        storage.verify_on_read = options.verify_on_read;
+        let mut file = OpenOptions::new().read(true).write(true).open(file_path)?;
+        if let Some(cache_capacity) = options.cache_capacity {
+            if Self::is_compressed_file(&mut file)? {
+                return Err(StorageError::CompressedFile);
+            }
+            storage.file = Some(Mutex::new(file));
+            storage.cache = Some(Mutex::new(PageCache::new(cache_capacity)));
+            return Ok(storage);
+        }
+        if Self::is_compressed_file(&mut file)? {
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            let mut offset = 0;
+            while offset < bytes.len() {
+                let page_id = storage.pages.read().unwrap().len() as u16;
+                let (page, len) =
+                    Page::read_compressed(page_id, storage.page_size, &bytes[offset..])?;
+                storage.add_page(page);
+                offset += len;
+            }
+            storage.compressed = true;
         } else {
-            let file = File::create(file_path)?;
-            let page = Page::new(page_size, None);
-            (file, page)
+            let num_pages = Self::num_file_pages(&file, storage.page_size)?;
+            for page_id in 0..num_pages {
+                let page = Self::load_unverified(&mut file, page_id, storage.page_size)?;
+                if storage.verify_on_read {
+                    page.verify()?;
+                }
+                storage.add_page(page);
+            }
+        }
+        storage.file = Some(Mutex::new(file));
+        Ok(storage)
+    }
+
+    // Peeks at the start of the file and leaves it positioned at the start.
+    fn is_compressed_file(file: &mut File) -> Result<bool> {
+        let mut head = Vec::new();
+        file.seek(SeekFrom::Start(0))?;
+        Read::by_ref(file).take(64).read_to_end(&mut head)?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(Page::is_compressed(&head))
+    }
+
+    /// Reads the whole file into memory with a single read, for read-heavy use
+    /// of large files. Rows are decoded straight from that one buffer through
+    /// `PageView`s, without copying each page into a buffer of its own, and
+    /// the returned storage cannot be written to.
+    pub fn open_read_only(file_path: &str) -> Result<ReadOnlyStorage> {
+        Self::open_read_only_with_page_size(file_path, PAGE_SIZE)
+    }
+
+    /// Like `open_read_only`, for a file written with pages of `page_size`
+    /// bytes.
+    pub fn open_read_only_with_page_size(
+        file_path: &str,
+        page_size: usize,
+    ) -> Result<ReadOnlyStorage> {
+        Self::check_page_size(page_size)?;
+        Self::recover(file_path, page_size)?;
+        let bytes = std::fs::read(file_path)?;
+        if Page::is_compressed(&bytes) {
+            return Err(StorageError::CompressedFile);
+        }
+        Self::num_whole_pages(bytes.len() as u64, page_size)?;
+        Ok(ReadOnlyStorage { bytes, page_size })
+    }
+
+    /// Like `open`, but first takes an exclusive advisory lock on the file,
+    /// failing with `Locked` if another handle already holds one. The lock is
+    /// released when the storage is dropped.
+    pub fn open_exclusive(file_path: &str) -> Result<Storage> {
+        let file = OpenOptions::new().read(true).write(true).open(file_path)?;
+        file.try_lock().map_err(|error| match error {
+            TryLockError::WouldBlock => StorageError::Locked,
+            TryLockError::Error(error) => StorageError::Io(error),
+        })?;
+        let mut storage = Self::open(file_path)?;
+        storage.file = Some(Mutex::new(file));
+        Ok(storage)
+    }
+
+    /// Opens the file without reading any pages up front. Pages are loaded on
+    /// demand by `page` and at most `cache_capacity` of them are kept in
+    /// memory, evicting the least recently used. Inserts write through to the
+    /// file, so evicted pages never hold unsaved rows.
+    pub fn open_cached(file_path: &str, cache_capacity: usize) -> Result<Storage> {
+        Self::open_cached_with_page_size(file_path, PAGE_SIZE, cache_capacity)
+    }
+
+    /// Like `open_cached`, for a file written with pages of `page_size` bytes.
+    pub fn open_cached_with_page_size(
+        file_path: &str,
+        page_size: usize,
+        cache_capacity: usize,
+    ) -> Result<Storage> {
+        Self::open_with_options(
+            file_path,
+            &StorageOptions {
+                page_size,
+                cache_capacity: Some(cache_capacity),
+                ..StorageOptions::default()
+            },
+        )
+    }
+
+    /// Calls `on_write` with the page id and encoded row size after every
+    /// successful `insert`.
+    pub fn set_on_write(&mut self, on_write: impl Fn(u16, usize) + Send + Sync + 'static) {
+        self.on_write = Some(Box::new(on_write));
+    }
+
+    /// Calls `on_read` with the page id and number of rows after every
+    /// successful `read`.
+    pub fn set_on_read(&mut self, on_read: impl Fn(u16, usize) + Send + Sync + 'static) {
+        self.on_read = Some(Box::new(on_read));
+    }
+
+    /// Writes the file with every page serialized by `Page::to_compressed`,
+    /// one after another, instead of at fixed offsets. This shrinks files of
+    /// repetitive text, but pages can no longer be found by offset: every
+    /// `insert` and `flush` rewrites the whole file, and only `open` can read
+    /// it back. The static functions and `open_cached` reject such a file
+    /// with `CompressedFile`.
+    pub fn set_compressed(&mut self, compressed: bool) {
+        self.compressed = compressed;
+    }
+
+    /// Sets the text encoding of pages allocated by `insert` from now on.
+    /// Existing pages keep the encoding recorded in their headers.
+    pub fn set_text_encoding(&mut self, text_encoding: TextEncoding) {
+        self.text_encoding = text_encoding;
+    }
+
+    /// Whether pages loaded from the file from now on have their checksum
+    /// verified. `open` loads every page before this can be called, so pass
+    /// `verify_on_read` to `open_with_options` to open a file without
+    /// verifying it. The static functions that take a file path always
+    /// verify.
+    pub fn set_verify_on_read(&mut self, verify_on_read: bool) {
+        self.verify_on_read = verify_on_read;
+    }
+
+    pub fn page(&self, page_id: u16) -> Result<Arc<Page>> {
+        match &self.cache {
+            Some(cache) => self.cached_page(&mut cache.lock().unwrap(), page_id),
+            None => self
+                .pages
+                .read()
+                .unwrap()
+                .get(page_id as usize)
+                .cloned()
+                .ok_or(StorageError::PageNotFound(page_id)),
+        }
+    }
+
+    fn cached_page(&self, cache: &mut PageCache, page_id: u16) -> Result<Arc<Page>> {
+        if let Some(page) = cache.get(page_id) {
+            return Ok(page);
+        }
+        let mut file = self.file()?;
+        if page_id as usize >= Self::num_file_pages(&file, self.page_size)? {
+            return Err(StorageError::PageNotFound(page_id));
+        }
+        let page = Self::load_unverified(&mut *file, page_id as usize, self.page_size)?;
+        if self.verify_on_read {
+            page.verify()?;
+        }
+        let page = Arc::new(page);
+        cache.insert(page_id, Arc::clone(&page));
+        Ok(page)
+    }
+
+    fn file(&self) -> Result<MutexGuard<'_, File>> {
+        match &self.file {
+            Some(file) => Ok(file.lock().unwrap()),
+            None => Err(StorageError::NoFile),
+        }
+    }
+
+    fn num_file_pages(file: &File, page_size: usize) -> Result<usize> {
+        Self::num_whole_pages(file.metadata()?.len(), page_size)
+    }
+
+    fn num_stream_pages<S: Seek>(io: &mut S, page_size: usize) -> Result<usize> {
+        Self::num_whole_pages(io.seek(SeekFrom::End(0))?, page_size)
+    }
+
+    // A length that is not a whole number of pages means the last page was
+    // cut short, usually by a crash mid-write.
+    fn num_whole_pages(len: u64, page_size: usize) -> Result<usize> {
+        let page_size = page_size as u64;
+        if !len.is_multiple_of(page_size) {
+            return Err(StorageError::TruncatedFile {
+                expected: len.next_multiple_of(page_size),
+                found: len,
+            });
+        }
+        Ok((len / page_size) as usize)
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Appends a row to the first page of the file with room for it.
+    ///
+    /// The page is written back with a single `write`; the data may still sit
+    /// in the OS page cache when this returns. Use `insert_data_durable` when
+    /// the row must survive a crash.
+    pub fn insert_data(file_path: &str, data_fields: &[DataField]) -> Result<()> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        Self::insert_into_file(file_path, data_fields)?;
+        Ok(())
+    }
+
+    /// Like `insert_data`, but on any seekable stream laid out as a storage
+    /// file, such as a `Cursor<Vec<u8>>`.
+    pub fn insert_data_into<S: Read + Write + Seek>(
+        io: &mut S,
+        data_fields: &[DataField],
+    ) -> Result<()> {
+        Self::insert_data_into_with_page_size(io, data_fields, PAGE_SIZE)
+    }
+
+    /// Like `insert_data_into`, for a stream of pages `page_size` bytes long.
+    pub fn insert_data_into_with_page_size<S: Read + Write + Seek>(
+        io: &mut S,
+        data_fields: &[DataField],
+        page_size: usize,
+    ) -> Result<()> {
+        Self::check_page_size(page_size)?;
+        Self::insert_into_stream(io, data_fields, None, page_size)
+    }
+
+    /// Like `insert_data`, but does nothing and returns `false` if an equal
+    /// row is already stored in the file.
+    pub fn insert_unique(file_path: &str, data_fields: &[DataField]) -> Result<bool> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(file_path)?;
+        for page_id in 0..Self::num_file_pages(&file, PAGE_SIZE)? {
+            let rows = Self::load_rows(&mut file, page_id, PAGE_SIZE)?;
+            if rows.iter().any(|row| row == data_fields) {
+                return Ok(false);
+            }
+        }
+        Self::insert_into_stream(&mut file, data_fields, None, PAGE_SIZE)?;
+        Ok(true)
+    }
+
+    /// Creates a new file laid out as `num_pages` empty pages, so inserts fill
+    /// them in order before the file has to grow. Fails if the file exists.
+    pub fn create_sized(file_path: &str, num_pages: usize) -> Result<()> {
+        let page_size = PAGE_SIZE;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(file_path)?;
+        file.set_len((num_pages * page_size) as u64)?;
+        for page_id in 0..num_pages {
+            let page = Page::new(page_id as u16, page_size, None);
+            Self::store_page(&mut file, &page, page_id, page_size)?;
+        }
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Appends `rows` in one pass over the file: the last page is filled
+    /// first, then new pages are appended, and each page is written once. A
+    /// row that has to spill to overflow pages writes the current page early
+    /// so the overflow pages land after it.
+    pub fn insert_many(file_path: &str, rows: &[Vec<DataField>]) -> Result<()> {
+        let mut writer = Self::writer(file_path)?;
+        for data_fields in rows {
+            writer.push(data_fields)?;
+        }
+        writer.finish()
+    }
+
+    /// Opens a `StorageWriter` that appends to the file, creating it if
+    /// needed. The last page is filled first.
+    pub fn writer(file_path: &str) -> Result<StorageWriter> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let page_size = PAGE_SIZE;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(file_path)?;
+        let num_pages = Self::num_file_pages(&file, page_size)?;
+        let mut current = None;
+        if num_pages > 0 {
+            let page = Self::load_page(&mut file, num_pages - 1, page_size)?;
+            if !page.is_overflow() {
+                current = Some((num_pages - 1, page));
+            }
+        }
+        Ok(StorageWriter {
+            file,
+            page_size,
+            current,
+            empty_page: Page::new(0, page_size, None),
+        })
+    }
+
+    /// Like `insert_data`, but issues `fsync` (`File::sync_all`) on the file
+    /// before returning so the row and file metadata are on stable storage.
+    pub fn insert_data_durable(file_path: &str, data_fields: &[DataField]) -> Result<()> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let file = Self::insert_into_file(file_path, data_fields)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Like `insert_data`, but first records the row in `<file>.wal` so that
+    /// a crash while the page is being rewritten can be repaired by `open`.
+    pub fn insert_data_logged(file_path: &str, data_fields: &[DataField]) -> Result<()> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let mut wal = WriteAheadLog::open(&WriteAheadLog::path_for(file_path))?;
+        let file = Self::insert_into_file_logged(file_path, data_fields, Some(&mut wal))?;
+        file.sync_all()?;
+        wal.truncate()?;
+        Ok(())
+    }
+
+    /// Builds the updated file at `<file>.tmp` and renames it over the
+    /// original, so readers see either the old or the new file, never a torn
+    /// page. The temp file is removed if anything fails before the rename.
+    pub fn insert_data_atomic(file_path: &str, data_fields: &[DataField]) -> Result<()> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let tmp_path = format!("{}.tmp", file_path);
+        let result = Self::write_tmp_copy(file_path, &tmp_path, data_fields)
+            .and_then(|()| Ok(std::fs::rename(&tmp_path, file_path)?));
+        if result.is_err() && Path::new(&tmp_path).is_file() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+        result
+    }
+
+    /// Rewrites the file with its live rows packed densely, dropping deleted
+    /// rows, empty pages and unreferenced overflow pages. The new file is
+    /// built at `<file>.tmp` and renamed over the original.
+    pub fn vacuum(file_path: &str) -> Result<()> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let tmp_path = format!("{}.tmp", file_path);
+        let result = Self::write_vacuumed_copy(file_path, &tmp_path)
+            .and_then(|()| Ok(std::fs::rename(&tmp_path, file_path)?));
+        if result.is_err() && Path::new(&tmp_path).is_file() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+        result
+    }
+
+    fn write_vacuumed_copy(file_path: &str, tmp_path: &str) -> Result<()> {
+        let mut file = File::open(file_path)?;
+        File::create(tmp_path)?;
+        let mut writer = Self::writer(tmp_path)?;
+        for page_id in 0..Self::num_file_pages(&file, PAGE_SIZE)? {
+            for row in Self::load_rows(&mut file, page_id, PAGE_SIZE)? {
+                writer.push(&row)?;
+            }
+        }
+        writer.finish()?;
+        File::open(tmp_path)?.sync_all()?;
+        Ok(())
+    }
+
+    fn write_tmp_copy(file_path: &str, tmp_path: &str, data_fields: &[DataField]) -> Result<()> {
+        if Path::new(file_path).exists() {
+            std::fs::copy(file_path, tmp_path)?;
+        } else {
+            File::create(tmp_path)?;
+        }
+        let file = Self::insert_into_file(tmp_path, data_fields)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    fn insert_into_file(file_path: &str, data_fields: &[DataField]) -> Result<File> {
+        Self::insert_into_file_logged(file_path, data_fields, None)
+    }
+
+    fn insert_into_file_logged(
+        file_path: &str,
+        data_fields: &[DataField],
+        wal: Option<&mut WriteAheadLog>,
+    ) -> Result<File> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(file_path)?;
+        Self::insert_into_stream(&mut file, data_fields, wal, PAGE_SIZE)?;
+        Ok(file)
+    }
+
+    fn insert_into_stream<S: Read + Write + Seek>(
+        io: &mut S,
+        data_fields: &[DataField],
+        wal: Option<&mut WriteAheadLog>,
+        page_size: usize,
+    ) -> Result<()> {
+        let data_fields = &Self::spill_oversized(io, data_fields, page_size)?;
+        let num_pages = Self::num_stream_pages(io, page_size)?;
+        let mut target = None;
+        for page_id in 0..num_pages {
+            let page = Self::load_page(io, page_id, page_size)?;
+            if !page.is_overflow() && page.can_fit(data_fields) {
+                target = Some((page_id, page));
+                break;
+            }
+        }
+        let (page_id, page) =
+            target.unwrap_or_else(|| (num_pages, Page::new(num_pages as u16, page_size, None)));
+        if let Some(wal) = wal {
+            wal.append(page_id as u16, page.next_slot() as u16, data_fields)?;
+        }
+        page.write(data_fields)?;
+        Self::store_page(io, &page, page_id, page_size)
+    }
+
+    // Replaces the largest fields with overflow references until the row fits
+    // in an empty page. Each spilled payload is written to a chain of new
+    // overflow pages at the end of the file.
+    fn spill_oversized<S: Write + Seek>(
+        io: &mut S,
+        data_fields: &[DataField],
+        page_size: usize,
+    ) -> Result<Vec<DataField>> {
+        let empty_page = Page::new(0, page_size, None);
+        let mut data_fields = data_fields.to_vec();
+        let reference_size = row_size(&[DataField::Overflow {
+            datatype_num: 0,
+            page_id: 0,
+            len: 0,
+        }]);
+        while !empty_page.can_fit(&data_fields) {
+            let (index, _) = data_fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| (index, row_size(std::slice::from_ref(field))))
+                .filter(|&(_, size)| size > reference_size)
+                .max_by_key(|&(_, size)| size)
+                .ok_or(PageError::PageFull)?;
+            data_fields[index] = Self::write_overflow(io, &data_fields[index], page_size)?;
+        }
+        Ok(data_fields)
+    }
+
+    // Each overflow page holds one row: a `Bytes` chunk of the payload and
+    // the id of the next page in the chain, or `Null` for the last one.
+    fn write_overflow<S: Write + Seek>(
+        io: &mut S,
+        field: &DataField,
+        page_size: usize,
+    ) -> Result<DataField> {
+        let payload = Page::encode_payload(field)?;
+        let chunk_len = Page::new_overflow(0, page_size).free_space()
+            - row_size(&[DataField::Bytes(Vec::new()), DataField::Integer(0)])
+            - 2;
+        let first_page_id = Self::num_stream_pages(io, page_size)?;
+        let chunks: Vec<&[u8]> = payload.chunks(chunk_len).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let page_id = first_page_id + i;
+            let next = if i + 1 < chunks.len() {
+                DataField::Integer(page_id as u16 + 1)
+            } else {
+                DataField::Null
+            };
+            let page = Page::new_overflow(page_id as u16, page_size);
+            page.write(&[DataField::Bytes(chunk.to_vec()), next])?;
+            Self::store_page(io, &page, page_id, page_size)?;
+        }
+        Ok(DataField::Overflow {
+            datatype_num: field.to_int(),
+            page_id: first_page_id as u16,
+            len: payload.len() as u32,
+        })
+    }
+
+    fn read_overflow<S: Read + Seek>(
+        io: &mut S,
+        datatype_num: u16,
+        first_page_id: u16,
+        len: u32,
+        page_size: usize,
+    ) -> Result<DataField> {
+        let len = len as usize;
+        let mut payload = Vec::with_capacity(len);
+        let mut next = Some(first_page_id);
+        while let Some(page_id) = next {
+            let broken = StorageError::BrokenOverflowChain(page_id);
+            if page_id as usize >= Self::num_stream_pages(io, page_size)? {
+                return Err(broken);
+            }
+            let page = Self::load_page(io, page_id as usize, page_size)?;
+            if !page.is_overflow() {
+                return Err(broken);
+            }
+            next = match page.get_row(0)?.as_slice() {
+                [DataField::Bytes(chunk), DataField::Integer(next)] if !chunk.is_empty() => {
+                    payload.extend_from_slice(chunk);
+                    Some(*next)
+                }
+                [DataField::Bytes(chunk), DataField::Null] => {
+                    payload.extend_from_slice(chunk);
+                    None
+                }
+                _ => return Err(broken),
+            };
+            if payload.len() > len {
+                return Err(broken);
+            }
+        }
+        if payload.len() != len {
+            return Err(StorageError::BrokenOverflowChain(first_page_id));
+        }
+        Ok(Page::decode_payload(datatype_num, &payload, first_page_id)?)
+    }
+
+    // Decodes the rows of one page with overflowed fields reassembled.
+    // Overflow pages themselves hold no rows of their own.
+    fn load_rows<S: Read + Seek>(
+        io: &mut S,
+        page_id: usize,
+        page_size: usize,
+    ) -> Result<Vec<Vec<DataField>>> {
+        let page = Self::load_page(io, page_id, page_size)?;
+        Self::page_rows(io, &page, page_size)
+    }
+
+    fn page_rows<S: Read + Seek>(
+        io: &mut S,
+        page: &Page,
+        page_size: usize,
+    ) -> Result<Vec<Vec<DataField>>> {
+        if page.is_overflow() {
+            return Ok(Vec::new());
+        }
+        Self::resolve_overflow(io, page.read()?, page_size)
+    }
+
+    // Replaces every overflow reference in `rows` with the field it stands for.
+    fn resolve_overflow<S: Read + Seek>(
+        io: &mut S,
+        rows: Vec<Vec<DataField>>,
+        page_size: usize,
+    ) -> Result<Vec<Vec<DataField>>> {
+        rows.into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|field| match field {
+                        DataField::Overflow {
+                            datatype_num,
+                            page_id,
+                            len,
+                        } => Self::read_overflow(io, datatype_num, page_id, len, page_size),
+                        field => Ok(field),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Entries whose slot already holds a row were applied before the crash
+    // and are skipped, so replaying the same log twice is harmless. Every
+    // function that reads or changes a file by path runs this first, since a
+    // change made before the log is applied can make an entry look applied
+    // when it is not, or bring a deleted row back.
+    fn recover(file_path: &str, page_size: usize) -> Result<()> {
+        let wal_path = WriteAheadLog::path_for(file_path);
+        if !Path::new(&wal_path).exists() {
+            return Ok(());
+        }
+        let mut wal = WriteAheadLog::open(&wal_path)?;
+        let entries = wal.replay()?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(file_path)?;
+        for entry in entries {
+            let page_id = entry.page_id as usize;
+            let num_pages = file.metadata()?.len() as usize / page_size;
+            let page = if page_id < num_pages {
+                Self::load_page(&mut file, page_id, page_size)?
+            } else {
+                Page::new(entry.page_id, page_size, None)
+            };
+            if page.get_row(entry.slot as usize).is_err() {
+                page.write(&entry.data_fields)?;
+                Self::store_page(&mut file, &page, page_id, page_size)?;
+            }
+        }
+        file.sync_all()?;
+        wal.truncate()?;
+        Ok(())
+    }
+
+    pub fn read_data(file_path: &str) -> Result<Vec<Vec<DataField>>> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        Self::read_data_from(&mut File::open(file_path)?)
+    }
+
+    /// Like `read_data`, but on any seekable stream laid out as a storage
+    /// file.
+    pub fn read_data_from<S: Read + Seek>(io: &mut S) -> Result<Vec<Vec<DataField>>> {
+        Self::read_data_from_with_page_size(io, PAGE_SIZE)
+    }
+
+    /// Like `read_data_from`, for a stream of pages `page_size` bytes long.
+    pub fn read_data_from_with_page_size<S: Read + Seek>(
+        io: &mut S,
+        page_size: usize,
+    ) -> Result<Vec<Vec<DataField>>> {
+        Self::check_page_size(page_size)?;
+        let num_pages = Self::num_stream_pages(io, page_size)?;
+        let mut rows = Vec::new();
+        for page_id in 0..num_pages {
+            rows.extend(Self::load_rows(io, page_id, page_size)?);
+        }
+        Ok(rows)
+    }
+
+    /// Reads and decodes the single page `page_id` without loading the rest
+    /// of the file.
+    pub fn read_page(file_path: &str, page_id: u16) -> Result<Vec<Vec<DataField>>> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let mut file = File::open(file_path)?;
+        if page_id as usize >= Self::num_file_pages(&file, PAGE_SIZE)? {
+            return Err(StorageError::PageNotFound(page_id));
+        }
+        Self::load_rows(&mut file, page_id as usize, PAGE_SIZE)
+    }
+
+    /// The distinct values of field `field_index` across every page, in
+    /// first-seen order.
+    pub fn distinct(file_path: &str, field_index: usize) -> Result<Vec<DataField>> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let mut file = File::open(file_path)?;
+        let mut values = Vec::new();
+        for page_id in 0..Self::num_file_pages(&file, PAGE_SIZE)? {
+            let page = Self::load_page(&mut file, page_id, PAGE_SIZE)?;
+            if page.is_overflow() {
+                continue;
+            }
+            for value in page.distinct(field_index)? {
+                let value = match value {
+                    DataField::Overflow {
+                        datatype_num,
+                        page_id,
+                        len,
+                    } => Self::read_overflow(&mut file, datatype_num, page_id, len, PAGE_SIZE)?,
+                    value => value,
+                };
+                push_distinct(&mut values, value);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Every row whose field `field_index` equals `value`. Pages whose bloom
+    /// filter rules the value out are skipped without decoding their rows.
+    pub fn find(
+        file_path: &str,
+        field_index: usize,
+        value: &DataField,
+    ) -> Result<Vec<Vec<DataField>>> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let mut file = File::open(file_path)?;
+        let mut rows = Vec::new();
+        for page_id in 0..Self::num_file_pages(&file, PAGE_SIZE)? {
+            let page = Self::load_page(&mut file, page_id, PAGE_SIZE)?;
+            if !page.might_contain(field_index, value) {
+                continue;
+            }
+            rows.extend(
+                Self::page_rows(&mut file, &page, PAGE_SIZE)?
+                    .into_iter()
+                    .filter(|row| row.get(field_index) == Some(value)),
+            );
+        }
+        Ok(rows)
+    }
+
+    /// Reads every row and renders them as a JSON array of arrays.
+    pub fn read_data_json(file_path: &str) -> Result<String> {
+        Ok(json::rows_to_json(&Self::read_data(file_path)?))
+    }
+
+    /// Streams rows across every page of the file, loading one page at a time.
+    pub fn iter_rows(file_path: &str) -> Result<RowCursor> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let file = File::open(file_path)?;
+        let num_pages = Self::num_file_pages(&file, PAGE_SIZE)?;
+        Ok(RowCursor {
+            file,
+            page_size: PAGE_SIZE,
+            num_pages,
+            next_page_id: 0,
+            rows: Vec::new().into_iter(),
+        })
+    }
+
+    /// Tombstones every row matching `pred` and returns how many were removed.
+    /// Only pages that lost a row are written back.
+    pub fn delete_data<F: Fn(&[DataField]) -> bool>(file_path: &str, pred: F) -> Result<usize> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let page_size = PAGE_SIZE;
+        let mut file = OpenOptions::new().read(true).write(true).open(file_path)?;
+        let num_pages = Self::num_file_pages(&file, page_size)?;
+        let mut deleted = 0;
+        for page_id in 0..num_pages {
+            let page = Self::load_page(&mut file, page_id, page_size)?;
+            if page.is_overflow() {
+                continue;
+            }
+            let page_deleted = page.delete_where(&pred)?;
+            if page_deleted > 0 {
+                Self::store_page(&mut file, &page, page_id, page_size)?;
+                deleted += page_deleted;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Drops trailing pages with no live rows, stopping at the first page that
+    /// still has one, and shrinks the file to match. Returns the number of
+    /// pages removed.
+    pub fn truncate_empty_tail(file_path: &str) -> Result<usize> {
+        Self::recover(file_path, PAGE_SIZE)?;
+        let page_size = PAGE_SIZE;
+        let mut file = OpenOptions::new().read(true).write(true).open(file_path)?;
+        let num_pages = Self::num_file_pages(&file, page_size)?;
+        let mut kept = num_pages;
+        while kept > 0 && Self::load_page(&mut file, kept - 1, page_size)?.count() == 0 {
+            kept -= 1;
+        }
+        if kept < num_pages {
+            file.set_len((kept * page_size) as u64)?;
+            file.sync_all()?;
+        }
+        Ok(num_pages - kept)
+    }
+
+    fn load_page<S: Read + Seek>(io: &mut S, page_id: usize, page_size: usize) -> Result<Page> {
+        let page = Self::load_unverified(io, page_id, page_size)?;
+        page.verify()?;
+        Ok(page)
+    }
+
+    // Reads a page and checks its format but not its checksum.
+    fn load_unverified<S: Read + Seek>(
+        io: &mut S,
+        page_id: usize,
+        page_size: usize,
+    ) -> Result<Page> {
+        let mut buffer = vec![0u8; page_size];
+        io.seek(SeekFrom::Start((page_id * page_size) as u64))?;
+        io.read_exact(&mut buffer)?;
+        if Page::is_compressed(&buffer) {
+            return Err(StorageError::CompressedFile);
+        }
+        let page = Page::new(page_id as u16, page_size, Some(buffer));
+        page.check_format()?;
+        Ok(page)
+    }
+
+    fn store_page<S: Write + Seek>(
+        io: &mut S,
+        page: &Page,
+        page_id: usize,
+        page_size: usize,
+    ) -> Result<()> {
+        page.seal();
+        io.seek(SeekFrom::Start((page_id * page_size) as u64))?;
+        page.with_buffer(|buffer| io.write_all(buffer))?;
+        Ok(())
+    }
+
+    pub fn insert(&self, data_fields: &[DataField]) -> Result<usize> {
+        Ok(self.insert_located(data_fields)?.page_id as usize)
+    }
+
+    /// Like `insert`, but returns the page and slot the row landed in.
+    pub fn insert_located(&self, data_fields: &[DataField]) -> Result<RowId> {
+        let row_id = match &self.cache {
+            Some(cache) => self.insert_cached(&mut cache.lock().unwrap(), data_fields)?,
+            None => self.insert_loaded(data_fields)?,
+        };
+        if let Some(on_write) = &self.on_write {
+            on_write(row_id.page_id, row_size(data_fields));
+        }
+        Ok(row_id)
+    }
+
+    /// Decodes the row at `row_id`.
+    pub fn get_row(&self, row_id: RowId) -> Result<Vec<DataField>> {
+        Ok(self.page(row_id.page_id)?.get_row(row_id.slot as usize)?)
+    }
+
+    /// Decodes every row of page `page_id`.
+    pub fn read(&self, page_id: u16) -> Result<Vec<Vec<DataField>>> {
+        let rows = self.page(page_id)?.read()?;
+        if let Some(on_read) = &self.on_read {
+            on_read(page_id, rows.len());
+        }
+        Ok(rows)
+    }
+
+    fn insert_loaded(&self, data_fields: &[DataField]) -> Result<RowId> {
+        // Held for the whole insert so concurrent callers can't both claim the
+        // last free space in a page or allocate the same new page id.
+        let mut pages = self.pages.write().unwrap();
+        let (page_id, slot) = match pages
+            .iter()
+            .position(|page| !page.is_overflow() && page.can_fit(data_fields))
+        {
+            Some(page_id) => (page_id, pages[page_id].write_located(data_fields)?),
+            None => {
+                let page = Page::with_text_encoding(
+                    pages.len() as u16,
+                    self.page_size,
+                    self.text_encoding,
+                );
+                let slot = page.write_located(data_fields)?;
+                pages.push(Arc::new(page));
+                (pages.len() - 1, slot)
+            }
         };
-        page.write(data_fields);
-        file.seek(std::io::SeekFrom::Start(0))?;
-        file.write_all(&page.get_buffer())?;
+        if let Some(file) = &self.file {
+            let mut file = file.lock().unwrap();
+            if self.compressed {
+                Self::store_compressed(&mut file, &pages)?;
+            } else {
+                Self::store_page(&mut *file, &pages[page_id], page_id, self.page_size)?;
+            }
+        }
+        Ok(RowId {
+            page_id: page_id as u16,
+            slot: slot as u16,
+        })
+    }
 
+    fn insert_cached(&self, cache: &mut PageCache, data_fields: &[DataField]) -> Result<RowId> {
+        let num_pages = Self::num_file_pages(&*self.file()?, self.page_size)?;
+        for page_id in 0..num_pages {
+            let page = self.cached_page(cache, page_id as u16)?;
+            if !page.is_overflow() && page.can_fit(data_fields) {
+                let slot = page.write_located(data_fields)?;
+                Self::store_page(&mut *self.file()?, &page, page_id, self.page_size)?;
+                return Ok(RowId {
+                    page_id: page_id as u16,
+                    slot: slot as u16,
+                });
+            }
+        }
+        let page = Arc::new(Page::with_text_encoding(
+            num_pages as u16,
+            self.page_size,
+            self.text_encoding,
+        ));
+        let slot = page.write_located(data_fields)?;
+        Self::store_page(&mut *self.file()?, &page, num_pages, self.page_size)?;
+        cache.insert(num_pages as u16, page);
+        Ok(RowId {
+            page_id: num_pages as u16,
+            slot: slot as u16,
+        })
+    }
+
+    pub fn flush(&self, file_path: &str) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(file_path)?;
+        let pages = self.pages.read().unwrap();
+        if self.compressed {
+            Self::store_compressed(&mut file, &pages)?;
+        } else {
+            for page in pages.iter() {
+                Self::store_page(&mut file, page, page.id() as usize, self.page_size)?;
+            }
+        }
+        file.sync_all()?;
         Ok(())
     }
 
-    pub fn read_data(file_path: &str) {}
+    // Replaces the file contents with every page compressed, back to back.
+    fn store_compressed(file: &mut File, pages: &[Arc<Page>]) -> Result<()> {
+        file.seek(SeekFrom::Start(0))?;
+        for page in pages {
+            file.write_all(&page.to_compressed())?;
+        }
+        let len = file.stream_position()?;
+        file.set_len(len)?;
+        Ok(())
+    }
 
     fn add_page(&self, page: Page) {
         let mut pages = self.pages.write().unwrap();
         pages.push(Arc::new(page));
     }
 }
+
+/// Appends rows to a file while holding only the page being filled in
+/// memory. Each page is written once, when it is full; `finish` writes the
+/// last one, so rows pushed since the last full page are lost if the writer
+/// is dropped without it.
+pub struct StorageWriter {
+    file: File,
+    page_size: usize,
+    current: Option<(usize, Page)>,
+    empty_page: Page,
+}
+
+impl StorageWriter {
+    /// Adds a row to the current page, first writing the page out and
+    /// starting a new one if the row does not fit. A row that has to spill
+    /// to overflow pages writes the current page early so the overflow pages
+    /// land after it.
+    pub fn push(&mut self, data_fields: &[DataField]) -> Result<()> {
+        let page_size = self.page_size;
+        let spilled;
+        let data_fields = if self.empty_page.can_fit(data_fields) {
+            data_fields
+        } else {
+            if let Some((page_id, page)) = &self.current {
+                Storage::store_page(&mut self.file, page, *page_id, page_size)?;
+            }
+            spilled = Storage::spill_oversized(&mut self.file, data_fields, page_size)?;
+            &spilled
+        };
+        if !matches!(&self.current, Some((_, page)) if page.can_fit(data_fields)) {
+            if let Some((page_id, page)) = self.current.take() {
+                Storage::store_page(&mut self.file, &page, page_id, page_size)?;
+            }
+            let page_id = Storage::num_file_pages(&self.file, page_size)?;
+            self.current = Some((page_id, Page::new(page_id as u16, page_size, None)));
+        }
+        if let Some((_, page)) = &self.current {
+            page.write(data_fields)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the partially filled last page.
+    pub fn finish(mut self) -> Result<()> {
+        if let Some((page_id, page)) = self.current.take() {
+            Storage::store_page(&mut self.file, &page, page_id, self.page_size)?;
+        }
+        Ok(())
+    }
+}
+
+/// A storage file held in memory by `Storage::open_read_only`.
+pub struct ReadOnlyStorage {
+    bytes: Vec<u8>,
+    page_size: usize,
+}
+
+impl ReadOnlyStorage {
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    pub fn num_pages(&self) -> usize {
+        self.bytes.len() / self.page_size
+    }
+
+    /// A verified view of page `page_id`, borrowing the storage's buffer.
+    pub fn page(&self, page_id: u16) -> Result<PageView<'_>> {
+        let start = page_id as usize * self.page_size;
+        let buffer = self
+            .bytes
+            .get(start..start + self.page_size)
+            .ok_or(StorageError::PageNotFound(page_id))?;
+        Ok(PageView::new(buffer, true)?)
+    }
+
+    /// The rows of page `page_id`, with overflowed fields read back in full.
+    /// Overflow pages themselves hold no rows.
+    pub fn read(&self, page_id: u16) -> Result<Vec<Vec<DataField>>> {
+        let page = self.page(page_id)?;
+        if page.is_overflow() {
+            return Ok(Vec::new());
+        }
+        let mut io = io::Cursor::new(&self.bytes[..]);
+        Storage::resolve_overflow(&mut io, page.read()?, self.page_size)
+    }
+
+    /// Every row of the file, as `Storage::read_data` returns them.
+    pub fn read_all(&self) -> Result<Vec<Vec<DataField>>> {
+        let mut rows = Vec::new();
+        for page_id in 0..self.num_pages() {
+            rows.extend(self.read(page_id as u16)?);
+        }
+        Ok(rows)
+    }
+}
+
+pub struct RowCursor {
+    file: File,
+    page_size: usize,
+    num_pages: usize,
+    next_page_id: usize,
+    rows: std::vec::IntoIter<Vec<DataField>>,
+}
+
+impl Iterator for RowCursor {
+    type Item = Result<Vec<DataField>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.rows.next() {
+                return Some(Ok(row));
+            }
+            if self.next_page_id >= self.num_pages {
+                return None;
+            }
+            let page_id = self.next_page_id;
+            self.next_page_id += 1;
+            let rows = Storage::load_rows(&mut self.file, page_id, self.page_size);
+            match rows {
+                Ok(rows) => self.rows = rows.into_iter(),
+                Err(err) => {
+                    // Stop after the first bad page rather than skipping it.
+                    self.next_page_id = self.num_pages;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "simplified_storage_{}_{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_insert_and_read_data() {
+        let file_path = temp_file_path("insert_and_read_data");
+        let first = vec![DataField::Text("first".to_string()), DataField::Integer(1)];
+        let second = vec![DataField::Text("second".to_string()), DataField::Integer(2)];
+        Storage::insert_data(&file_path, &first).unwrap();
+        Storage::insert_data(&file_path, &second).unwrap();
+        assert_eq!(Storage::read_data(&file_path).unwrap(), vec![first, second]);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_data_allocates_new_pages() {
+        let file_path = temp_file_path("insert_data_allocates_new_pages");
+        let rows: Vec<Vec<DataField>> = (0..600)
+            .map(|i| vec![DataField::Integer(i), DataField::Text(format!("row {}", i))])
+            .collect();
+        for row in &rows {
+            Storage::insert_data(&file_path, row).unwrap();
+        }
+        let file_len = std::fs::metadata(&file_path).unwrap().len();
+        assert!(file_len > PAGE_SIZE as u64);
+        assert_eq!(file_len % PAGE_SIZE as u64, 0);
+        assert_eq!(Storage::read_data(&file_path).unwrap(), rows);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_open_loads_every_page() {
+        let file_path = temp_file_path("open_loads_every_page");
+        for i in 0..600 {
+            Storage::insert_data(&file_path, &[DataField::Integer(i), DataField::Int64(0)])
+                .unwrap();
+        }
+        let num_pages = std::fs::metadata(&file_path).unwrap().len() as usize / PAGE_SIZE;
+        assert!(num_pages > 1);
+        let storage = Storage::open(&file_path).unwrap();
+        assert_eq!(storage.pages.read().unwrap().len(), num_pages);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_grows_pages() {
+        let storage = Storage::with_page_size(256).unwrap();
+        let rows: Vec<Vec<DataField>> = (0..100)
+            .map(|i| vec![DataField::Integer(i), DataField::Text(format!("row {}", i))])
+            .collect();
+        let mut page_ids = Vec::new();
+        for row in &rows {
+            page_ids.push(storage.insert(row).unwrap());
+        }
+        let pages = storage.pages.read().unwrap();
+        assert!(pages.len() > 1);
+        assert_eq!(*page_ids.last().unwrap(), pages.len() - 1);
+        let stored: Vec<Vec<DataField>> =
+            pages.iter().flat_map(|page| page.read().unwrap()).collect();
+        assert_eq!(stored, rows);
+    }
+
+    #[test]
+    fn test_flush_and_reopen() {
+        let file_path = temp_file_path("flush_and_reopen");
+        let storage = Storage::with_page_size(PAGE_SIZE).unwrap();
+        let rows: Vec<Vec<DataField>> = (0..600)
+            .map(|i| vec![DataField::Integer(i), DataField::Text(format!("row {}", i))])
+            .collect();
+        for row in &rows {
+            storage.insert(row).unwrap();
+        }
+        storage.flush(&file_path).unwrap();
+        let reopened = Storage::open(&file_path).unwrap();
+        assert_eq!(
+            reopened.pages.read().unwrap().len(),
+            storage.pages.read().unwrap().len()
+        );
+        assert_eq!(Storage::read_data(&file_path).unwrap(), rows);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_data_durable() {
+        let file_path = temp_file_path("insert_data_durable");
+        let row = vec![DataField::Text("durable".to_string())];
+        Storage::insert_data_durable(&file_path, &row).unwrap();
+        Storage::insert_data(&file_path, &row).unwrap();
+        assert_eq!(
+            Storage::read_data(&file_path).unwrap(),
+            vec![row.clone(), row]
+        );
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_inserts() {
+        let file_path = temp_file_path("concurrent_inserts");
+        File::create(&file_path).unwrap();
+        let storage = Arc::new(Storage::open(&file_path).unwrap());
+        let handles: Vec<_> = (0..8)
+            .map(|thread_id| {
+                let storage = Arc::clone(&storage);
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        storage
+                            .insert(&[DataField::Integer(thread_id), DataField::Integer(i)])
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let mut rows = Storage::read_data(&file_path).unwrap();
+        assert_eq!(rows.len(), 800);
+        rows.sort_by_key(|row| match (&row[0], &row[1]) {
+            (DataField::Integer(thread_id), DataField::Integer(i)) => (*thread_id, *i),
+            _ => panic!("unexpected row"),
+        });
+        rows.dedup();
+        assert_eq!(rows.len(), 800);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_delete_data() {
+        let file_path = temp_file_path("delete_data");
+        let rows: Vec<Vec<DataField>> = (0..600)
+            .map(|i| vec![DataField::Integer(i), DataField::Text(format!("row {}", i))])
+            .collect();
+        for row in &rows {
+            Storage::insert_data(&file_path, row).unwrap();
+        }
+        let deleted = Storage::delete_data(&file_path, |row| {
+            row[0] == DataField::Integer(10) || row[0] == DataField::Integer(590)
+        })
+        .unwrap();
+        assert_eq!(deleted, 2);
+        let expected: Vec<Vec<DataField>> = rows
+            .into_iter()
+            .filter(|row| row[0] != DataField::Integer(10) && row[0] != DataField::Integer(590))
+            .collect();
+        assert_eq!(Storage::read_data(&file_path).unwrap(), expected);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_data_logged() {
+        let file_path = temp_file_path("insert_data_logged");
+        let row = vec![DataField::Text("logged".to_string())];
+        Storage::insert_data_logged(&file_path, &row).unwrap();
+        let wal_path = WriteAheadLog::path_for(&file_path);
+        assert_eq!(std::fs::metadata(&wal_path).unwrap().len(), 0);
+        assert_eq!(Storage::read_data(&file_path).unwrap(), vec![row]);
+        std::fs::remove_file(&file_path).unwrap();
+        std::fs::remove_file(&wal_path).unwrap();
+    }
+
+    #[test]
+    fn test_open_replays_wal_after_crash() {
+        let file_path = temp_file_path("open_replays_wal_after_crash");
+        let wal_path = WriteAheadLog::path_for(&file_path);
+        let _ = std::fs::remove_file(&wal_path);
+        let first = vec![DataField::Integer(1)];
+        let second = vec![DataField::Integer(2)];
+        Storage::insert_data_logged(&file_path, &first).unwrap();
+        // A crash after the page was written but before the log was truncated.
+        let mut wal = WriteAheadLog::open(&wal_path).unwrap();
+        wal.append(0, 0, &first).unwrap();
+        // A crash after the row was logged but before the page was written.
+        wal.append(0, 1, &second).unwrap();
+        let storage = Storage::open(&file_path).unwrap();
+        assert_eq!(
+            storage.pages.read().unwrap()[0].read().unwrap(),
+            vec![first, second]
+        );
+        assert!(wal.replay().unwrap().is_empty());
+        std::fs::remove_file(&file_path).unwrap();
+        std::fs::remove_file(&wal_path).unwrap();
+    }
+
+    #[test]
+    fn test_static_functions_apply_the_wal_first() {
+        let file_path = temp_file_path("static_functions_apply_the_wal_first");
+        let wal_path = WriteAheadLog::path_for(&file_path);
+        let _ = std::fs::remove_file(&wal_path);
+        let applied = vec![DataField::Integer(1)];
+        Storage::insert_data(&file_path, &applied).unwrap();
+        // Applied before a crash, but the log was never truncated.
+        WriteAheadLog::open(&wal_path)
+            .unwrap()
+            .append(0, 0, &applied)
+            .unwrap();
+        assert_eq!(Storage::delete_data(&file_path, |_| true).unwrap(), 1);
+        assert!(Storage::open(&file_path)
+            .unwrap()
+            .read(0)
+            .unwrap()
+            .is_empty());
+
+        // Logged before a crash, but never written to its page.
+        let logged = vec![DataField::Integer(2)];
+        let plain = vec![DataField::Integer(3)];
+        WriteAheadLog::open(&wal_path)
+            .unwrap()
+            .append(0, 1, &logged)
+            .unwrap();
+        Storage::insert_data(&file_path, &plain).unwrap();
+        assert_eq!(Storage::read_data(&file_path).unwrap(), vec![logged, plain]);
+        assert_eq!(Storage::open(&file_path).unwrap().read(0).unwrap().len(), 2);
+        std::fs::remove_file(&file_path).unwrap();
+        std::fs::remove_file(&wal_path).unwrap();
+    }
+
+    #[test]
+    fn test_open_with_page_size_replays_wal() {
+        let file_path = temp_file_path("open_with_page_size_replays_wal");
+        let wal_path = WriteAheadLog::path_for(&file_path);
+        let _ = std::fs::remove_file(&wal_path);
+        let storage = Storage::with_page_size(1024).unwrap();
+        storage.insert(&[DataField::Integer(1)]).unwrap();
+        storage.flush(&file_path).unwrap();
+        let logged = vec![DataField::Integer(2)];
+        WriteAheadLog::open(&wal_path)
+            .unwrap()
+            .append(0, 1, &logged)
+            .unwrap();
+        let reopened = Storage::open_with_page_size(&file_path, 1024).unwrap();
+        assert_eq!(
+            reopened.read(0).unwrap(),
+            vec![vec![DataField::Integer(1)], logged]
+        );
+        assert_eq!(std::fs::metadata(&file_path).unwrap().len(), 1024);
+        std::fs::remove_file(&file_path).unwrap();
+        std::fs::remove_file(&wal_path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_data_atomic() {
+        let file_path = temp_file_path("insert_data_atomic");
+        let first = vec![DataField::Integer(1)];
+        let second = vec![DataField::Integer(2)];
+        Storage::insert_data_atomic(&file_path, &first).unwrap();
+        Storage::insert_data_atomic(&file_path, &second).unwrap();
+        assert_eq!(Storage::read_data(&file_path).unwrap(), vec![first, second]);
+        assert!(!Path::new(&format!("{}.tmp", file_path)).exists());
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_data_atomic_failure_leaves_original() {
+        let file_path = temp_file_path("insert_data_atomic_failure");
+        Storage::insert_data(&file_path, &[DataField::Integer(1)]).unwrap();
+        let original = std::fs::read(&file_path).unwrap();
+        let too_long = [DataField::Text("a".repeat(70_000))];
+        assert!(Storage::insert_data_atomic(&file_path, &too_long).is_err());
+        assert_eq!(std::fs::read(&file_path).unwrap(), original);
+        let tmp_path = format!("{}.tmp", file_path);
+        assert!(!Path::new(&tmp_path).exists());
+        // A directory in the way makes the temp copy itself fail.
+        std::fs::create_dir(&tmp_path).unwrap();
+        assert!(Storage::insert_data_atomic(&file_path, &[DataField::Integer(2)]).is_err());
+        assert_eq!(std::fs::read(&file_path).unwrap(), original);
+        std::fs::remove_dir(&tmp_path).unwrap();
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_open_cached_loads_pages_on_demand() {
+        let file_path = temp_file_path("open_cached_loads_pages_on_demand");
+        for i in 0..1500 {
+            Storage::insert_data(&file_path, &[DataField::Integer(i), DataField::Int64(0)])
+                .unwrap();
+        }
+        let eager = Storage::open(&file_path).unwrap();
+        let num_pages = eager.pages.read().unwrap().len();
+        assert!(num_pages > 2);
+        let storage = Storage::open_cached(&file_path, 2).unwrap();
+        for _ in 0..2 {
+            for page_id in 0..num_pages as u16 {
+                assert_eq!(
+                    storage.page(page_id).unwrap().read().unwrap(),
+                    eager.page(page_id).unwrap().read().unwrap()
+                );
+                assert!(storage.cache.as_ref().unwrap().lock().unwrap().len() <= 2);
+            }
+        }
+        assert!(matches!(
+            storage.page(num_pages as u16),
+            Err(StorageError::PageNotFound(page_id)) if page_id as usize == num_pages
+        ));
+        let page_id = storage.insert(&[DataField::Integer(7)]).unwrap();
+        assert!(storage
+            .page(page_id as u16)
+            .unwrap()
+            .read()
+            .unwrap()
+            .contains(&vec![DataField::Integer(7)]));
+        assert_eq!(Storage::read_data(&file_path).unwrap().len(), 1501);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_iter_rows_streams_pages_in_order() {
+        let file_path = temp_file_path("iter_rows_streams_pages_in_order");
+        let mut expected = Vec::new();
+        for i in 0..1200 {
+            let data_fields = vec![DataField::Integer(i), DataField::Int64(i as i64)];
+            Storage::insert_data(&file_path, &data_fields).unwrap();
+            expected.push(data_fields);
+        }
+        let num_pages = std::fs::metadata(&file_path).unwrap().len() as usize / PAGE_SIZE;
+        assert_eq!(num_pages, 3);
+        let rows = Storage::iter_rows(&file_path)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(rows, expected);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_read_data_json() {
+        let file_path = temp_file_path("read_data_json");
+        let rows = vec![
+            vec![DataField::Integer(1), DataField::Text("a,b".to_string())],
+            vec![DataField::Null, DataField::Bool(true)],
+        ];
+        for row in &rows {
+            Storage::insert_data(&file_path, row).unwrap();
+        }
+        let json = Storage::read_data_json(&file_path).unwrap();
+        assert_eq!(json, "[[1,\"a,b\"],[null,true]]");
+        assert_eq!(json::rows_from_json(&json).unwrap(), rows);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_read_page() {
+        let file_path = temp_file_path("read_page");
+        for i in 0..1000 {
+            Storage::insert_data(&file_path, &[DataField::Integer(i), DataField::Int64(0)])
+                .unwrap();
+        }
+        let storage = Storage::open(&file_path).unwrap();
+        let expected = storage.page(1).unwrap().read().unwrap();
+        assert!(!expected.is_empty());
+        assert_eq!(Storage::read_page(&file_path, 1).unwrap(), expected);
+        assert!(matches!(
+            Storage::read_page(&file_path, 3),
+            Err(StorageError::PageNotFound(3))
+        ));
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_distinct_across_pages() {
+        let file_path = temp_file_path("distinct_across_pages");
+        let labels = ["c", "a", "b", "a"];
+        let rows: Vec<Vec<DataField>> = (0..1000)
+            .map(|i| {
+                vec![
+                    DataField::Integer(i as u16),
+                    DataField::Text(labels[i % labels.len()].to_string()),
+                ]
+            })
+            .collect();
+        Storage::insert_many(&file_path, &rows).unwrap();
+        assert!(Storage::read_page(&file_path, 1).is_ok());
+        assert_eq!(
+            Storage::distinct(&file_path, 1).unwrap(),
+            vec![
+                DataField::Text("c".to_string()),
+                DataField::Text("a".to_string()),
+                DataField::Text("b".to_string()),
+            ]
+        );
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_truncate_empty_tail() {
+        let file_path = temp_file_path("truncate_empty_tail");
+        let rows: Vec<Vec<DataField>> = (0..1200)
+            .map(|i| vec![DataField::Integer(i), DataField::Int64(0)])
+            .collect();
+        Storage::insert_many(&file_path, &rows).unwrap();
+        let len = std::fs::metadata(&file_path).unwrap().len();
+        assert_eq!(len, 3 * PAGE_SIZE as u64);
+        let first_page = Storage::read_page(&file_path, 0).unwrap();
+        let first_page_max = match first_page.last().unwrap()[0] {
+            DataField::Integer(i) => i,
+            _ => unreachable!(),
+        };
+        Storage::delete_data(
+            &file_path,
+            |row| matches!(row[0], DataField::Integer(i) if i > first_page_max),
+        )
+        .unwrap();
+        assert_eq!(Storage::truncate_empty_tail(&file_path).unwrap(), 2);
+        assert_eq!(
+            std::fs::metadata(&file_path).unwrap().len(),
+            len - 2 * PAGE_SIZE as u64
+        );
+        assert_eq!(Storage::read_data(&file_path).unwrap(), first_page);
+        assert_eq!(Storage::truncate_empty_tail(&file_path).unwrap(), 0);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_into_cursor() {
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        let rows: Vec<Vec<DataField>> = (0..600)
+            .map(|i| vec![DataField::Integer(i), DataField::Int64(-(i as i64))])
+            .collect();
+        for row in &rows {
+            Storage::insert_data_into(&mut cursor, row).unwrap();
+        }
+        let large_row = vec![DataField::Bytes(vec![9; 3 * PAGE_SIZE])];
+        Storage::insert_data_into(&mut cursor, &large_row).unwrap();
+        assert_eq!(cursor.get_ref().len() % PAGE_SIZE, 0);
+        let mut expected = rows;
+        expected.push(large_row);
+        assert_eq!(Storage::read_data_from(&mut cursor).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_hooks_count_inserts_and_reads() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let writes = Arc::new(AtomicUsize::new(0));
+        let bytes = Arc::new(AtomicUsize::new(0));
+        let rows_read = Arc::new(AtomicUsize::new(0));
+        let mut storage = Storage::with_page_size(256).unwrap();
+        let (writes_hook, bytes_hook) = (Arc::clone(&writes), Arc::clone(&bytes));
+        storage.set_on_write(move |_, len| {
+            writes_hook.fetch_add(1, Ordering::SeqCst);
+            bytes_hook.fetch_add(len, Ordering::SeqCst);
+        });
+        let rows_hook = Arc::clone(&rows_read);
+        storage.set_on_read(move |_, rows| {
+            rows_hook.fetch_add(rows, Ordering::SeqCst);
+        });
+        let row = [DataField::Integer(1), DataField::Int64(2)];
+        for _ in 0..20 {
+            storage.insert(&row).unwrap();
+        }
+        assert_eq!(writes.load(Ordering::SeqCst), 20);
+        assert_eq!(bytes.load(Ordering::SeqCst), 20 * row_size(&row));
+        let total = storage.read(0).unwrap().len() + storage.read(1).unwrap().len();
+        assert_eq!(total, 20);
+        assert_eq!(rows_read.load(Ordering::SeqCst), 20);
+        assert!(storage.read(5).is_err());
+        assert_eq!(rows_read.load(Ordering::SeqCst), 20);
+    }
+
+    #[test]
+    fn test_insert_unique() {
+        let file_path = temp_file_path("insert_unique");
+        let row = vec![DataField::Integer(1), DataField::Text("once".to_string())];
+        assert!(Storage::insert_unique(&file_path, &row).unwrap());
+        assert!(!Storage::insert_unique(&file_path, &row).unwrap());
+        let other = vec![DataField::Integer(1), DataField::Text("twice".to_string())];
+        assert!(Storage::insert_unique(&file_path, &other).unwrap());
+        assert_eq!(Storage::read_data(&file_path).unwrap(), vec![row, other]);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_open_exclusive() {
+        let file_path = temp_file_path("open_exclusive");
+        Storage::insert_data(&file_path, &[DataField::Integer(1)]).unwrap();
+        let storage = Storage::open_exclusive(&file_path).unwrap();
+        assert!(matches!(
+            Storage::open_exclusive(&file_path),
+            Err(StorageError::Locked)
+        ));
+        storage.insert(&[DataField::Integer(2)]).unwrap();
+        drop(storage);
+        let storage = Storage::open_exclusive(&file_path).unwrap();
+        assert_eq!(storage.read(0).unwrap().len(), 2);
+        drop(storage);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_storage_writer_streams_pages() {
+        let file_path = temp_file_path("storage_writer_streams_pages");
+        let mut writer = Storage::writer(&file_path).unwrap();
+        let row = |i: u16| vec![DataField::Integer(i), DataField::Int64(i as i64)];
+        for i in 0..2000 {
+            writer.push(&row(i)).unwrap();
+            // Only full pages have been written so far.
+            let len = std::fs::metadata(&file_path).unwrap().len() as usize;
+            assert_eq!(len / PAGE_SIZE, i as usize / 454);
+        }
+        writer.finish().unwrap();
+        assert_eq!(
+            Storage::read_data(&file_path).unwrap(),
+            (0..2000).map(row).collect::<Vec<_>>()
+        );
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_vacuum() {
+        let file_path = temp_file_path("vacuum");
+        let rows: Vec<Vec<DataField>> = (0..2000)
+            .map(|i| vec![DataField::Integer(i), DataField::Int64(0)])
+            .collect();
+        Storage::insert_many(&file_path, &rows).unwrap();
+        Storage::insert_data(&file_path, &[DataField::Bytes(vec![1; 2 * PAGE_SIZE])]).unwrap();
+        let len = std::fs::metadata(&file_path).unwrap().len();
+        Storage::delete_data(&file_path, |row| match row[0] {
+            DataField::Integer(i) => i % 2 == 0,
+            _ => true,
+        })
+        .unwrap();
+        Storage::vacuum(&file_path).unwrap();
+        let vacuumed_len = std::fs::metadata(&file_path).unwrap().len();
+        assert_eq!(vacuumed_len, 3 * PAGE_SIZE as u64);
+        assert!(vacuumed_len < len);
+        let survivors: Vec<_> = rows.into_iter().skip(1).step_by(2).collect();
+        assert_eq!(Storage::read_data(&file_path).unwrap(), survivors);
+        assert!(!Path::new(&format!("{}.tmp", file_path)).exists());
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_find_skips_filtered_pages() {
+        let file_path = temp_file_path("find_skips_filtered_pages");
+        let mut file = File::create(&file_path).unwrap();
+        for page_id in 0..3u16 {
+            let page = Page::with_bloom_filter(page_id, PAGE_SIZE, 0);
+            for i in 0..100 {
+                page.write(&[DataField::Integer(page_id * 100 + i)])
+                    .unwrap();
+            }
+            Storage::store_page(&mut file, &page, page_id as usize, PAGE_SIZE).unwrap();
+        }
+        drop(file);
+        let wanted = DataField::Integer(142);
+        let storage = Storage::open(&file_path).unwrap();
+        let candidates: Vec<u16> = (0..3)
+            .filter(|&page_id| storage.page(page_id).unwrap().might_contain(0, &wanted))
+            .collect();
+        assert!(candidates.contains(&1));
+        assert_eq!(
+            Storage::find(&file_path, 0, &wanted).unwrap(),
+            vec![vec![wanted.clone()]]
+        );
+        assert!(Storage::find(&file_path, 0, &DataField::Integer(999))
+            .unwrap()
+            .is_empty());
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_set_text_encoding_latin1() {
+        let file_path = temp_file_path("set_text_encoding_latin1");
+        File::create(&file_path).unwrap();
+        let mut storage = Storage::open(&file_path).unwrap();
+        storage.set_text_encoding(TextEncoding::Latin1);
+        let row = vec![DataField::Text("\u{e9}t\u{e9}".to_string())];
+        storage.insert(&row).unwrap();
+        drop(storage);
+        let bytes = std::fs::read(&file_path).unwrap();
+        assert!(bytes.windows(3).any(|window| window == b"\xE9t\xE9"));
+        let reopened = Storage::open(&file_path).unwrap();
+        assert_eq!(
+            reopened.page(0).unwrap().text_encoding(),
+            TextEncoding::Latin1
+        );
+        assert_eq!(reopened.read(0).unwrap(), vec![row]);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_truncated_file() {
+        let file_path = temp_file_path("truncated_file");
+        Storage::insert_data(&file_path, &[DataField::Integer(1)]).unwrap();
+        let file = OpenOptions::new().write(true).open(&file_path).unwrap();
+        file.set_len(PAGE_SIZE as u64 + 100).unwrap();
+        drop(file);
+        let expected = (2 * PAGE_SIZE) as u64;
+        let found = PAGE_SIZE as u64 + 100;
+        assert!(matches!(
+            Storage::open(&file_path),
+            Err(StorageError::TruncatedFile { expected: e, found: f }) if e == expected && f == found
+        ));
+        assert!(matches!(
+            Storage::read_data(&file_path),
+            Err(StorageError::TruncatedFile { expected: e, found: f }) if e == expected && f == found
+        ));
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_pages_get_sequential_ids() {
+        let file_path = temp_file_path("pages_get_sequential_ids");
+        let row = vec![DataField::Bytes(vec![0; 3000])];
+        for _ in 0..5 {
+            Storage::insert_data(&file_path, &row).unwrap();
+        }
+        let storage = Storage::open(&file_path).unwrap();
+        let num_pages = storage.pages.read().unwrap().len();
+        assert!(num_pages > 1);
+        for page_id in 0..num_pages as u16 {
+            let page = storage.page(page_id).unwrap();
+            assert_eq!(page.id(), page_id);
+            assert_eq!(page.header().id, page_id);
+        }
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_located() {
+        let storage = Storage::with_page_size(256).unwrap();
+        let rows: Vec<Vec<DataField>> = (0..40)
+            .map(|i| vec![DataField::Integer(i), DataField::Text(format!("row {}", i))])
+            .collect();
+        let row_ids: Vec<RowId> = rows
+            .iter()
+            .map(|row| storage.insert_located(row).unwrap())
+            .collect();
+        let distinct: std::collections::HashSet<_> = row_ids.iter().collect();
+        assert_eq!(distinct.len(), rows.len());
+        assert!(row_ids.iter().any(|row_id| row_id.page_id > 0));
+        for (row_id, row) in row_ids.iter().zip(&rows) {
+            assert_eq!(&storage.get_row(*row_id).unwrap(), row);
+        }
+        assert_eq!(
+            row_ids[1],
+            RowId {
+                page_id: 0,
+                slot: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_overflow_blobs_over_64_kib() {
+        let file_path = temp_file_path("overflow_blobs_over_64_kib");
+        let blob: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let rows = vec![
+            vec![DataField::Integer(1), DataField::Bytes(blob.clone())],
+            vec![DataField::CheckedBytes(blob)],
+        ];
+        for row in &rows {
+            Storage::insert_data(&file_path, row).unwrap();
+        }
+        assert_eq!(Storage::read_data(&file_path).unwrap(), rows);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_compressed_file_shrinks_on_disk() {
+        let plain_path = temp_file_path("compressed_file_plain");
+        let compressed_path = temp_file_path("compressed_file_compressed");
+        let rows: Vec<Vec<DataField>> = (0..600)
+            .map(|i| {
+                vec![
+                    DataField::Integer(i),
+                    DataField::Text("status: pending review by the storage team".to_string()),
+                ]
+            })
+            .collect();
+        let mut storage = Storage::with_page_size(PAGE_SIZE).unwrap();
+        for row in &rows {
+            storage.insert(row).unwrap();
+        }
+        storage.flush(&plain_path).unwrap();
+        storage.set_compressed(true);
+        storage.flush(&compressed_path).unwrap();
+        let plain_len = std::fs::metadata(&plain_path).unwrap().len();
+        let compressed_len = std::fs::metadata(&compressed_path).unwrap().len();
+        assert!(plain_len >= 4 * PAGE_SIZE as u64);
+        assert!(compressed_len < plain_len / 4);
+
+        let reopened = Storage::open(&compressed_path).unwrap();
+        let num_pages = reopened.pages.read().unwrap().len() as u16;
+        let read_back: Vec<_> = (0..num_pages)
+            .flat_map(|page_id| reopened.read(page_id).unwrap())
+            .collect();
+        assert_eq!(read_back, rows);
+        let extra = vec![DataField::Integer(9999)];
+        reopened.insert(&extra).unwrap();
+        drop(reopened);
+        assert!(std::fs::metadata(&compressed_path).unwrap().len() < plain_len / 4);
+        let reopened = Storage::open(&compressed_path).unwrap();
+        let num_pages = reopened.pages.read().unwrap().len() as u16;
+        assert!((0..num_pages).any(|page_id| reopened.read(page_id).unwrap().contains(&extra)));
+        assert!(matches!(
+            Storage::open_cached(&compressed_path, 1),
+            Err(StorageError::CompressedFile)
+        ));
+        std::fs::remove_file(&plain_path).unwrap();
+        std::fs::remove_file(&compressed_path).unwrap();
+    }
+
+    #[test]
+    fn test_flush_and_reopen_with_page_size() {
+        let file_path = temp_file_path("flush_and_reopen_with_page_size");
+        let storage = Storage::with_page_size(1024).unwrap();
+        let rows: Vec<_> = (0..100)
+            .map(|i| vec![DataField::Integer(i), DataField::Text("row".to_string())])
+            .collect();
+        for row in &rows {
+            storage.insert(row).unwrap();
+        }
+        storage.flush(&file_path).unwrap();
+        let num_pages = storage.pages.read().unwrap().len() as u16;
+        assert!(num_pages > 1);
+        assert_eq!(
+            std::fs::metadata(&file_path).unwrap().len(),
+            num_pages as u64 * 1024
+        );
+
+        let reopened = Storage::open_with_page_size(&file_path, 1024).unwrap();
+        assert_eq!(reopened.page_size(), 1024);
+        let read_back: Vec<_> = (0..num_pages)
+            .flat_map(|page_id| reopened.read(page_id).unwrap())
+            .collect();
+        assert_eq!(read_back, rows);
+        let cached = Storage::open_cached_with_page_size(&file_path, 1024, 1).unwrap();
+        assert_eq!(
+            cached.read(num_pages - 1).unwrap(),
+            reopened.read(num_pages - 1).unwrap()
+        );
+
+        let mut stream = std::io::Cursor::new(std::fs::read(&file_path).unwrap());
+        let extra = vec![DataField::Integer(500)];
+        Storage::insert_data_into_with_page_size(&mut stream, &extra, 1024).unwrap();
+        assert_eq!(stream.get_ref().len() % 1024, 0);
+        let mut expected = rows.clone();
+        expected.push(extra);
+        assert_eq!(
+            Storage::read_data_from_with_page_size(&mut stream, 1024).unwrap(),
+            expected
+        );
+        assert!(matches!(
+            Storage::read_data_from_with_page_size(&mut stream, 70000),
+            Err(StorageError::PageSizeTooLarge(70000))
+        ));
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_data_arrays_up_to_max_fields() {
+        let file_path = temp_file_path("insert_data_arrays_up_to_max_fields");
+        let too_many = vec![DataField::Array(vec![DataField::Null; 70000])];
+        assert!(matches!(
+            Storage::insert_data(&file_path, &too_many),
+            Err(StorageError::Page(PageError::TooManyFields {
+                count: 70000
+            }))
+        ));
+        let largest = vec![DataField::Array(vec![DataField::Null; page::MAX_FIELDS])];
+        Storage::insert_data(&file_path, &largest).unwrap();
+        assert_eq!(Storage::read_data(&file_path).unwrap(), vec![largest]);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_open_read_only() {
+        let file_path = temp_file_path("open_read_only");
+        for i in 0..1000 {
+            Storage::insert_data(
+                &file_path,
+                &[DataField::Integer(i), DataField::Text(format!("row {}", i))],
+            )
+            .unwrap();
+        }
+        Storage::insert_data(&file_path, &[DataField::Bytes(vec![3; 2 * PAGE_SIZE])]).unwrap();
+        let storage = Storage::open_read_only(&file_path).unwrap();
+        assert_eq!(
+            storage.num_pages() as u64 * PAGE_SIZE as u64,
+            std::fs::metadata(&file_path).unwrap().len()
+        );
+        assert_eq!(
+            storage.read_all().unwrap(),
+            Storage::read_data(&file_path).unwrap()
+        );
+        assert_eq!(
+            storage.read(0).unwrap(),
+            Storage::read_page(&file_path, 0).unwrap()
+        );
+        assert!(matches!(
+            storage.read(storage.num_pages() as u16),
+            Err(StorageError::PageNotFound(_))
+        ));
+
+        let mut bytes = std::fs::read(&file_path).unwrap();
+        bytes[PAGE_SIZE - 1] ^= 1;
+        std::fs::write(&file_path, &bytes).unwrap();
+        let storage = Storage::open_read_only(&file_path).unwrap();
+        assert!(matches!(
+            storage.read(0),
+            Err(StorageError::Page(PageError::ChecksumMismatch))
+        ));
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_data_spills_large_fields_to_overflow_pages() {
+        let file_path = temp_file_path("insert_data_spills_large_fields");
+        let blob: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        let large_row = vec![
+            DataField::Integer(1),
+            DataField::Bytes(blob),
+            DataField::Text("after".to_string()),
+        ];
+        Storage::insert_data(&file_path, &[DataField::Integer(0)]).unwrap();
+        Storage::insert_data(&file_path, &large_row).unwrap();
+        Storage::insert_data(&file_path, &[DataField::Integer(2)]).unwrap();
+        // Page 0 plus three overflow pages for the 20000-byte blob.
+        assert_eq!(
+            std::fs::metadata(&file_path).unwrap().len() as usize / PAGE_SIZE,
+            4
+        );
+        let expected = vec![
+            vec![DataField::Integer(0)],
+            large_row,
+            vec![DataField::Integer(2)],
+        ];
+        assert_eq!(Storage::read_data(&file_path).unwrap(), expected);
+        let streamed = Storage::iter_rows(&file_path)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(streamed, expected);
+        assert!(Storage::read_page(&file_path, 1).unwrap().is_empty());
+
+        // Cutting the chain short is reported rather than returning a
+        // truncated blob.
+        let bytes = std::fs::read(&file_path).unwrap();
+        std::fs::write(&file_path, &bytes[..3 * PAGE_SIZE]).unwrap();
+        assert!(matches!(
+            Storage::read_data(&file_path),
+            Err(StorageError::BrokenOverflowChain(3))
+        ));
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_many() {
+        let file_path = temp_file_path("insert_many");
+        Storage::insert_data(&file_path, &[DataField::Integer(0)]).unwrap();
+        let rows: Vec<Vec<DataField>> = (1..3000)
+            .map(|i| vec![DataField::Integer(i), DataField::Text(format!("row {}", i))])
+            .collect();
+        Storage::insert_many(&file_path, &rows).unwrap();
+        let mut expected = vec![vec![DataField::Integer(0)]];
+        expected.extend(rows);
+        assert_eq!(Storage::read_data(&file_path).unwrap(), expected);
+
+        let num_pages = std::fs::metadata(&file_path).unwrap().len() as usize / PAGE_SIZE;
+        let blob_row = vec![DataField::Bytes(vec![7; 10_000])];
+        Storage::insert_many(&file_path, &[blob_row.clone(), vec![DataField::Integer(1)]]).unwrap();
+        expected.push(blob_row);
+        expected.push(vec![DataField::Integer(1)]);
+        assert_eq!(Storage::read_data(&file_path).unwrap(), expected);
+        // The blob's two overflow pages follow the existing pages.
+        assert_eq!(
+            std::fs::metadata(&file_path).unwrap().len() as usize / PAGE_SIZE,
+            num_pages + 2
+        );
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_create_sized() {
+        let file_path = temp_file_path("create_sized");
+        Storage::create_sized(&file_path, 4).unwrap();
+        assert_eq!(
+            std::fs::metadata(&file_path).unwrap().len(),
+            4 * PAGE_SIZE as u64
+        );
+        for page_id in 0..4 {
+            assert!(Storage::read_page(&file_path, page_id).unwrap().is_empty());
+        }
+        for i in 0..1000 {
+            Storage::insert_data(&file_path, &[DataField::Integer(i), DataField::Int64(0)])
+                .unwrap();
+        }
+        assert_eq!(
+            std::fs::metadata(&file_path).unwrap().len(),
+            4 * PAGE_SIZE as u64
+        );
+        assert!(!Storage::read_page(&file_path, 2).unwrap().is_empty());
+        assert!(matches!(
+            Storage::create_sized(&file_path, 1),
+            Err(StorageError::Io(error)) if error.kind() == io::ErrorKind::AlreadyExists
+        ));
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_read_data_empty_page() {
+        let file_path = temp_file_path("read_data_empty_page");
+        let page = Page::new(0, PAGE_SIZE, None);
+        page.seal();
+        std::fs::write(&file_path, &*page.get_buffer()).unwrap();
+        assert!(Storage::read_data(&file_path).unwrap().is_empty());
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_read_data_detects_corruption() {
+        let file_path = temp_file_path("read_data_detects_corruption");
+        Storage::insert_data(&file_path, &[DataField::Integer(1)]).unwrap();
+        let mut bytes = std::fs::read(&file_path).unwrap();
+        bytes[PAGE_SIZE - 1] ^= 0xFF;
+        std::fs::write(&file_path, bytes).unwrap();
+        assert!(Storage::read_data(&file_path).is_err());
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    fn page_error(error: &StorageError) -> Option<&PageError> {
+        match error {
+            StorageError::Page(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_verify_on_read() {
+        let file_path = temp_file_path("verify_on_read");
+        let row = vec![DataField::Integer(1)];
+        Storage::insert_data(&file_path, &row).unwrap();
+        let mut bytes = std::fs::read(&file_path).unwrap();
+        // The checksum is the last field of the header.
+        let checksum = Page::new(0, PAGE_SIZE, None).header().end_headers as usize - 1;
+        bytes[checksum] ^= 0xFF;
+        std::fs::write(&file_path, bytes).unwrap();
+        let mut storage = Storage::open_cached(&file_path, 1).unwrap();
+        let error = storage.read(0).unwrap_err();
+        assert_eq!(page_error(&error), Some(&PageError::ChecksumMismatch));
+        storage.set_verify_on_read(false);
+        assert_eq!(storage.read(0).unwrap(), vec![row.clone()]);
+
+        let error = Storage::open(&file_path).err().unwrap();
+        assert_eq!(page_error(&error), Some(&PageError::ChecksumMismatch));
+        let unverified = StorageOptions {
+            verify_on_read: false,
+            ..StorageOptions::default()
+        };
+        let storage = Storage::open_with_options(&file_path, &unverified).unwrap();
+        assert_eq!(storage.read(0).unwrap(), vec![row.clone()]);
+        let cached = StorageOptions {
+            cache_capacity: Some(1),
+            ..unverified
+        };
+        let storage = Storage::open_with_options(&file_path, &cached).unwrap();
+        assert_eq!(storage.read(0).unwrap(), vec![row]);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_read_data_checks_format() {
+        let file_path = temp_file_path("read_data_checks_format");
+        Storage::insert_data(&file_path, &[DataField::Integer(1)]).unwrap();
+        assert!(Storage::read_data(&file_path).is_ok());
+        let mut bytes = std::fs::read(&file_path).unwrap();
+        bytes[4] = 99;
+        std::fs::write(&file_path, &bytes).unwrap();
+        let error = Storage::open(&file_path).err().unwrap();
+        assert_eq!(page_error(&error), Some(&PageError::UnsupportedVersion(99)));
+        bytes[..4].copy_from_slice(b"JUNK");
+        std::fs::write(&file_path, &bytes).unwrap();
+        let error = Storage::read_data(&file_path).unwrap_err();
+        assert_eq!(page_error(&error), Some(&PageError::BadMagic));
+        std::fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn test_with_page_size() {
+        let storage = Storage::with_page_size(4096).unwrap();
+        assert_eq!(storage.page_size(), 4096);
+        assert!(Storage::with_page_size(u16::MAX as usize).is_ok());
+        let error = Storage::with_page_size(u16::MAX as usize + 1)
+            .err()
+            .unwrap();
+        assert!(matches!(error, StorageError::PageSizeTooLarge(65536)));
+    }
+
+    #[test]
+    fn test_read_data_missing_file() {
+        let file_path = temp_file_path("read_data_missing_file");
+        let error = Storage::read_data(&file_path).unwrap_err();
+        assert!(
+            matches!(error, StorageError::Io(error) if error.kind() == io::ErrorKind::NotFound)
+        );
+    }
+}
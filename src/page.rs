@@ -1,9 +1,160 @@
-use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::bloom;
+use crate::checksum::{crc32, Crc32};
+use crate::compression;
+use crate::json::{JsonValue, MAX_DEPTH};
+use crate::schema::Schema;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::atomic::{self, AtomicU16};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageError {
+    PageFull,
+    InvalidUtf8 { slot: usize },
+    UnknownType(u16),
+    ChecksumMismatch,
+    InvalidSlot(usize),
+    TextTooLong { len: usize },
+    FieldOutOfRange(usize),
+    BadMagic,
+    UnsupportedVersion(u8),
+    TypeMismatch { column: String },
+    ColumnCountMismatch { expected: usize, found: usize },
+    CorruptPage { offset: usize },
+    UnknownFlags(u8),
+    InvalidChar { slot: usize, value: u32 },
+    BlobChecksumMismatch { slot: usize },
+    InvalidJson { slot: usize },
+    UnencodableText { value: char },
+    WrongPageSize { expected: usize, found: usize },
+    BytesTooLong { len: usize },
+    JsonTooDeep,
+    TooManyFields { count: usize },
+    ArrayTooDeep,
+    OverflowChecksumMismatch { page_id: u16 },
+}
+
+impl fmt::Display for PageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageError::PageFull => write!(f, "row does not fit in the page"),
+            PageError::InvalidUtf8 { slot } => {
+                write!(f, "text in slot {} is not valid utf-8", slot)
+            }
+            PageError::UnknownType(datatype_num) => write!(f, "unknown type tag {}", datatype_num),
+            PageError::ChecksumMismatch => write!(f, "page checksum does not match its contents"),
+            PageError::InvalidSlot(slot) => write!(f, "slot {} does not hold a row", slot),
+            PageError::TextTooLong { len } => {
+                write!(f, "text of {} bytes exceeds the u16 length prefix", len)
+            }
+            PageError::FieldOutOfRange(field_index) => {
+                write!(f, "row has no field {}", field_index)
+            }
+            PageError::BadMagic => write!(f, "page does not start with the storage magic bytes"),
+            PageError::UnsupportedVersion(version) => {
+                write!(f, "unsupported page format version {}", version)
+            }
+            PageError::TypeMismatch { column } => {
+                write!(f, "value does not match the type of column {}", column)
+            }
+            PageError::ColumnCountMismatch { expected, found } => {
+                write!(f, "expected {} values but found {}", expected, found)
+            }
+            PageError::CorruptPage { offset } => {
+                write!(f, "corrupt page: read past the end at offset {}", offset)
+            }
+            PageError::UnknownFlags(flags) => write!(f, "unknown header flags {:#04x}", flags),
+            PageError::InvalidChar { slot, value } => {
+                write!(
+                    f,
+                    "char in slot {} has invalid scalar value {:#x}",
+                    slot, value
+                )
+            }
+            PageError::BlobChecksumMismatch { slot } => {
+                write!(
+                    f,
+                    "checksummed bytes in slot {} do not match their crc",
+                    slot
+                )
+            }
+            PageError::InvalidJson { slot } => write!(f, "json in slot {} does not parse", slot),
+            PageError::UnencodableText { value } => {
+                write!(f, "{:?} cannot be stored in the page text encoding", value)
+            }
+            PageError::WrongPageSize { expected, found } => {
+                write!(
+                    f,
+                    "expected a page of {} bytes but found {}",
+                    expected, found
+                )
+            }
+            PageError::BytesTooLong { len } => {
+                write!(f, "{} bytes exceed the u16 length prefix", len)
+            }
+            PageError::JsonTooDeep => write!(f, "json nests deeper than {} levels", MAX_DEPTH),
+            PageError::TooManyFields { count } => {
+                write!(f, "{} fields exceed the limit of {}", count, MAX_FIELDS)
+            }
+            PageError::ArrayTooDeep => {
+                write!(f, "arrays nest deeper than {} levels", MAX_ARRAY_DEPTH)
+            }
+            PageError::OverflowChecksumMismatch { page_id } => write!(f, "checksummed bytes in the overflow chain starting at page {} do not match their crc", page_id),
+        }
+    }
+}
+
+impl std::error::Error for PageError {}
+
+impl From<PageError> for std::io::Error {
+    fn from(error: PageError) -> Self {
+        std::io::Error::other(error)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum DataField {
     Text(String),
     Integer(u16),
+    Int32(i32),
+    Int64(i64),
+    Float64(f64),
+    Null,
+    Bool(bool),
+    Bytes(Vec<u8>),
+    LargeText(String),
+    UInt32(u32),
+    /// Microseconds since the Unix epoch.
+    Timestamp(i64),
+    Uuid([u8; 16]),
+    /// Unsigned LEB128: seven bits per byte, so values below 128 take one byte.
+    VarInt(u64),
+    /// Stands in for a field too large for one page. The field's encoded
+    /// payload is split across a chain of overflow pages starting at
+    /// `page_id`; `Storage` readers replace this with the original field.
+    Overflow {
+        datatype_num: u16,
+        page_id: u16,
+        len: u32,
+    },
+    /// Fixed-point number equal to `mantissa / 10^scale`, so `12.34` is
+    /// `{ mantissa: 1234, scale: 2 }`.
+    Decimal {
+        mantissa: i64,
+        scale: u8,
+    },
+    Char(char),
+    /// A list of fields, each stored with its own tag like the fields of a row.
+    Array(Vec<DataField>),
+    /// Like `Bytes`, but stored with a CRC32 of its contents that is checked
+    /// when the field is decoded.
+    CheckedBytes(Vec<u8>),
+    /// A JSON document, stored in its compact text form with a u32 length
+    /// prefix and parsed again when read.
+    Json(JsonValue),
+    /// Signed counterpart of `Integer`.
+    Int16(i16),
 }
 
 impl DataField {
@@ -11,18 +162,427 @@ impl DataField {
         match self {
             DataField::Integer(_) => 1,
             DataField::Text(_) => 2,
+            DataField::Int32(_) => 3,
+            DataField::Int64(_) => 4,
+            DataField::Float64(_) => 5,
+            DataField::Null => 6,
+            DataField::Bool(_) => 7,
+            DataField::Bytes(_) => 8,
+            DataField::LargeText(_) => 9,
+            DataField::UInt32(_) => 10,
+            DataField::Timestamp(_) => 11,
+            DataField::Uuid(_) => 12,
+            DataField::VarInt(_) => 13,
+            DataField::Overflow { .. } => 14,
+            DataField::Decimal { .. } => 15,
+            DataField::Char(_) => 16,
+            DataField::Array(_) => 17,
+            DataField::CheckedBytes(_) => 18,
+            DataField::Json(_) => 19,
+            DataField::Int16(_) => 20,
+        }
+    }
+
+    fn payload_len(&self) -> usize {
+        match self {
+            DataField::Integer(_) => 2,
+            DataField::Text(text) => 2 + text.len(),
+            DataField::Int32(_) => 4,
+            DataField::Int64(_) => 8,
+            DataField::Float64(_) => 8,
+            DataField::Null => 0,
+            DataField::Bool(_) => 1,
+            DataField::Bytes(bytes) => 2 + bytes.len(),
+            DataField::LargeText(text) => 4 + text.len(),
+            DataField::UInt32(_) => 4,
+            DataField::Timestamp(_) => 8,
+            DataField::Uuid(_) => 16,
+            DataField::VarInt(value) => varint_len(*value),
+            DataField::Overflow { .. } => 8,
+            DataField::Decimal { .. } => 9,
+            DataField::Char(_) => 4,
+            DataField::Array(elements) => row_size(elements),
+            DataField::CheckedBytes(bytes) => 6 + bytes.len(),
+            DataField::Json(value) => 4 + value.to_string().len(),
+            DataField::Int16(_) => 2,
+        }
+    }
+}
+
+impl fmt::Display for DataField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataField::Text(text) | DataField::LargeText(text) => write!(f, "{}", text),
+            DataField::Integer(int) => write!(f, "{}", int),
+            DataField::Int32(int) => write!(f, "{}", int),
+            DataField::Int64(int) => write!(f, "{}", int),
+            DataField::Float64(float) => write!(f, "{}", float),
+            DataField::Null => Ok(()),
+            DataField::Bool(bool) => write!(f, "{}", bool),
+            DataField::Bytes(bytes) | DataField::CheckedBytes(bytes) => {
+                bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+            }
+            DataField::UInt32(int) => write!(f, "{}", int),
+            DataField::Timestamp(micros) => write!(f, "{}", micros),
+            DataField::Uuid(bytes) => {
+                for (i, byte) in bytes.iter().enumerate() {
+                    if matches!(i, 4 | 6 | 8 | 10) {
+                        write!(f, "-")?;
+                    }
+                    write!(f, "{:02x}", byte)?;
+                }
+                Ok(())
+            }
+            DataField::VarInt(int) => write!(f, "{}", int),
+            DataField::Overflow { page_id, len, .. } => {
+                write!(f, "<{} bytes on overflow page {}>", len, page_id)
+            }
+            DataField::Decimal { mantissa, scale } => {
+                write!(f, "{}", format_decimal(*mantissa, *scale))
+            }
+            DataField::Char(c) => write!(f, "{}", c),
+            DataField::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+            DataField::Json(value) => write!(f, "{}", value),
+            DataField::Int16(int) => write!(f, "{}", int),
+        }
+    }
+}
+
+// Values are only `PartialEq`, so this is a linear scan.
+pub(crate) fn push_distinct(values: &mut Vec<DataField>, value: DataField) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+// The u16 length prefix of a `Bytes` or `CheckedBytes` payload.
+fn bytes_len(bytes: &[u8]) -> Result<u16, PageError> {
+    u16::try_from(bytes.len()).map_err(|_| PageError::BytesTooLong { len: bytes.len() })
+}
+
+/// Number of bytes `Page::write` stores for a row: the field count, one type
+/// tag per field and each payload with its length prefix. Every row also
+/// takes a 2-byte slot pointer, which is not included here.
+pub fn row_size(data_fields: &[DataField]) -> usize {
+    2 + data_fields
+        .iter()
+        .map(|field| 2 + field.payload_len())
+        .sum::<usize>()
+}
+
+/// Orders fields of the same variant: numbers numerically, text and bytes
+/// lexicographically, `false < true`, and `Null` equal only to itself.
+/// Fields of different variants are unordered, as are `Float64` NaNs, and
+/// `Decimal`s with different scales that hold the same value, since those
+/// compare unequal. `Overflow` references only compare equal to themselves.
+impl PartialOrd for DataField {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (DataField::Text(a), DataField::Text(b)) => a.partial_cmp(b),
+            (DataField::LargeText(a), DataField::LargeText(b)) => a.partial_cmp(b),
+            (DataField::Integer(a), DataField::Integer(b)) => a.partial_cmp(b),
+            (DataField::Int32(a), DataField::Int32(b)) => a.partial_cmp(b),
+            (DataField::Int64(a), DataField::Int64(b)) => a.partial_cmp(b),
+            (DataField::UInt32(a), DataField::UInt32(b)) => a.partial_cmp(b),
+            (DataField::Char(a), DataField::Char(b)) => a.partial_cmp(b),
+            (DataField::Int16(a), DataField::Int16(b)) => a.partial_cmp(b),
+            (DataField::Array(a), DataField::Array(b)) => a.partial_cmp(b),
+            (DataField::VarInt(a), DataField::VarInt(b)) => a.partial_cmp(b),
+            (DataField::Timestamp(a), DataField::Timestamp(b)) => a.partial_cmp(b),
+            (DataField::Float64(a), DataField::Float64(b)) => a.partial_cmp(b),
+            (DataField::Bool(a), DataField::Bool(b)) => a.partial_cmp(b),
+            (DataField::Bytes(a), DataField::Bytes(b)) => a.partial_cmp(b),
+            (DataField::CheckedBytes(a), DataField::CheckedBytes(b)) => a.partial_cmp(b),
+            (DataField::Uuid(a), DataField::Uuid(b)) => a.partial_cmp(b),
+            (DataField::Null, DataField::Null) => Some(Ordering::Equal),
+            (
+                DataField::Decimal {
+                    mantissa: a,
+                    scale: a_scale,
+                },
+                DataField::Decimal {
+                    mantissa: b,
+                    scale: b_scale,
+                },
+            ) => {
+                let scale = *a_scale.max(b_scale) as u32;
+                let widen = |mantissa: i64, from: u8| {
+                    10i128
+                        .checked_pow(scale - from as u32)
+                        .and_then(|factor| (mantissa as i128).checked_mul(factor))
+                };
+                let ordering = widen(*a, *a_scale)?.cmp(&widen(*b, *b_scale)?);
+                if ordering == Ordering::Equal && a_scale != b_scale {
+                    return None;
+                }
+                Some(ordering)
+            }
+            _ => (self == other).then_some(Ordering::Equal),
+        }
+    }
+}
+
+/// Formats `mantissa / 10^scale` with exactly `scale` decimal places.
+pub fn format_decimal(mantissa: i64, scale: u8) -> String {
+    let sign = if mantissa < 0 { "-" } else { "" };
+    let scale = scale as usize;
+    let digits = format!("{:0>width$}", mantissa.unsigned_abs(), width = scale + 1);
+    let (int, frac) = digits.split_at(digits.len() - scale);
+    if frac.is_empty() {
+        format!("{}{}", sign, int)
+    } else {
+        format!("{}{}.{}", sign, int, frac)
+    }
+}
+
+fn varint_len(value: u64) -> usize {
+    (64 - value.leading_zeros() as usize).div_ceil(7).max(1)
+}
+
+// Row data always lives below the header, so no live slot can point at offset 0.
+const TOMBSTONE: u16 = 0;
+
+pub const MAGIC: &[u8; 4] = b"STRG";
+
+// Version 1 was the original layout without magic bytes or a checksum;
+// version 2 added both but had no flags byte, and version 3 had no next page
+// pointer. Pages in any of them still read.
+pub const FORMAT_VERSION: u8 = 4;
+
+// Stored as the next page id of a page that ends its chain.
+const NO_NEXT_PAGE: u16 = u16::MAX;
+
+// Set in the field count of a row written by `write_ints`, whose fields are
+// all `Integer` and stored without type tags. Encoding rejects more than
+// `MAX_FIELDS` fields, so no tagged row or `Array`, in a page, an overflow
+// chain or a framed record, ever has this bit set.
+const PACKED_INTS: u16 = 0x8000;
+
+/// Most fields a row or `Array` can hold, since the high bit of the stored
+/// count is taken by `PACKED_INTS`.
+pub const MAX_FIELDS: usize = 0x7FFF;
+
+/// Most `Array`s that can nest inside one another. Writes past it fail with
+/// `ArrayTooDeep` and reads with `CorruptPage`, so decoding a crafted row
+/// cannot overflow the stack.
+pub const MAX_ARRAY_DEPTH: usize = 128;
+
+fn array_depth(data_fields: &[DataField]) -> usize {
+    data_fields
+        .iter()
+        .map(|field| match field {
+            DataField::Array(elements) => 1 + array_depth(elements),
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+// Bits of the header flags byte.
+const FLAG_BIG_ENDIAN: u8 = 0x01;
+const FLAG_OVERFLOW: u8 = 0x02;
+const FLAG_BLOOM: u8 = 0x04;
+const FLAG_LATIN1: u8 = 0x08;
+// Only ever set in the bytes produced by `to_compressed`, never in a page.
+const FLAG_COMPRESSED: u8 = 0x10;
+const KNOWN_FLAGS: u8 =
+    FLAG_BIG_ENDIAN | FLAG_OVERFLOW | FLAG_BLOOM | FLAG_LATIN1 | FLAG_COMPRESSED;
+
+// A page with a bloom filter reserves its last bytes for the filtered field
+// index (u16) followed by the filter bits, one byte per 32 bytes of page.
+fn filter_bits_len(page_size: usize) -> usize {
+    page_size / 32
+}
+
+// Start of that region, or `None` when the page is too small to hold any
+// filter bits below the end of its header.
+fn filter_start(page_size: usize, end_headers: u16) -> Option<usize> {
+    let bits_len = filter_bits_len(page_size);
+    if bits_len == 0 {
+        return None;
+    }
+    page_size
+        .checked_sub(2 + bits_len)
+        .filter(|&start| start >= end_headers as usize)
+}
+
+/// Byte order of every multi-byte value in a page. It is recorded in the
+/// header so a page opened from bytes reads back in the order it was written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endianness {
+    fn from_flags(flags: u8) -> Self {
+        if flags & FLAG_BIG_ENDIAN != 0 {
+            Endianness::Big
+        } else {
+            Endianness::Little
+        }
+    }
+
+    fn flags(self) -> u8 {
+        match self {
+            Endianness::Little => 0,
+            Endianness::Big => FLAG_BIG_ENDIAN,
+        }
+    }
+
+    // Converts little-endian bytes to this order, and back again.
+    fn order<const N: usize>(self, mut bytes: [u8; N]) -> [u8; N] {
+        if self == Endianness::Big {
+            bytes.reverse();
+        }
+        bytes
+    }
+}
+
+/// How `Text` and `LargeText` payloads are stored. Latin-1 keeps one byte
+/// per char, so legacy single-byte data always decodes, but can only hold
+/// chars up to U+00FF. The choice is recorded in the header flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Latin1,
+}
+
+impl TextEncoding {
+    fn from_flags(flags: u8) -> Self {
+        if flags & FLAG_LATIN1 != 0 {
+            TextEncoding::Latin1
+        } else {
+            TextEncoding::Utf8
+        }
+    }
+
+    fn flags(self) -> u8 {
+        match self {
+            TextEncoding::Utf8 => 0,
+            TextEncoding::Latin1 => FLAG_LATIN1,
+        }
+    }
+
+    fn encode(self, text: &str) -> Result<Vec<u8>, PageError> {
+        match self {
+            TextEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            TextEncoding::Latin1 => text
+                .chars()
+                .map(|value| u8::try_from(value).map_err(|_| PageError::UnencodableText { value }))
+                .collect(),
+        }
+    }
+
+    fn decode(self, bytes: &[u8], slot: usize) -> Result<String, PageError> {
+        match self {
+            TextEncoding::Utf8 => {
+                String::from_utf8(bytes.to_vec()).map_err(|_| PageError::InvalidUtf8 { slot })
+            }
+            TextEncoding::Latin1 => Ok(bytes.iter().map(|&byte| byte as char).collect()),
         }
     }
 }
 
 #[derive(Debug, Clone)]
 struct PageHeaderOffsets {
+    pub format_version: u8,
+    pub magic: Option<(usize, usize)>,
+    pub version: Option<usize>,
+    pub flags: Option<usize>,
     pub id: (usize, usize),
     pub lower: (usize, usize),
     pub higher: (usize, usize),
+    pub next_page: Option<(usize, usize)>,
+    pub checksum: Option<(usize, usize)>,
     pub end_headers: u16,
 }
 
+impl PageHeaderOffsets {
+    fn for_version(version: u8) -> Option<Self> {
+        match version {
+            1 => Some(Self {
+                format_version: 1,
+                magic: None,
+                version: None,
+                flags: None,
+                id: (0, 2),
+                lower: (2, 4),
+                higher: (4, 6),
+                next_page: None,
+                checksum: None,
+                end_headers: 6,
+            }),
+            2 => Some(Self {
+                format_version: 2,
+                magic: Some((0, 4)),
+                version: Some(4),
+                flags: None,
+                id: (5, 7),
+                lower: (7, 9),
+                higher: (9, 11),
+                next_page: None,
+                checksum: Some((11, 15)),
+                end_headers: 15,
+            }),
+            3 => Some(Self {
+                format_version: 3,
+                magic: Some((0, 4)),
+                version: Some(4),
+                flags: Some(5),
+                id: (6, 8),
+                lower: (8, 10),
+                higher: (10, 12),
+                next_page: None,
+                checksum: Some((12, 16)),
+                end_headers: 16,
+            }),
+            4 => Some(Self {
+                format_version: 4,
+                magic: Some((0, 4)),
+                version: Some(4),
+                flags: Some(5),
+                id: (6, 8),
+                lower: (8, 10),
+                higher: (10, 12),
+                next_page: Some((12, 14)),
+                checksum: Some((14, 18)),
+                end_headers: 18,
+            }),
+            _ => None,
+        }
+    }
+
+    fn current() -> Self {
+        Self::for_version(FORMAT_VERSION).unwrap()
+    }
+
+    /// The layout `buffer` was written with. Buffers without magic bytes
+    /// predate them and are read as version 1; an unknown version falls
+    /// back to the current layout so `check_format` can report it.
+    fn detect(buffer: &[u8]) -> Self {
+        if !buffer.starts_with(MAGIC) {
+            return Self::for_version(1).unwrap();
+        }
+        buffer
+            .get(4)
+            .filter(|&&version| version != 1)
+            .and_then(|&version| Self::for_version(version))
+            .unwrap_or_else(Self::current)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct PageHeader {
     pub id: u16,
@@ -30,32 +590,223 @@ struct PageHeader {
     pub higher: u16,
 }
 
+/// Copy of a page's header fields, for tooling that inspects the raw layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageHeaderInfo {
+    /// `None` for version 1 pages, which predate the magic bytes.
+    pub magic: Option<[u8; 4]>,
+    pub version: u8,
+    /// Always 0 before version 3.
+    pub flags: u8,
+    pub id: u16,
+    pub lower: u16,
+    pub higher: u16,
+    /// `None` before version 4, which added it.
+    pub next_page: Option<u16>,
+    /// `None` for version 1 pages, which had no checksum.
+    pub checksum: Option<u32>,
+    /// Offset of the first slot pointer, just past the header.
+    pub end_headers: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageStats {
+    pub num_rows: usize,
+    /// Header, slot pointers, live row data and any bloom filter.
+    pub used_bytes: usize,
+    /// The gap between the slot pointers and the row data.
+    pub free_bytes: usize,
+    /// Row data left behind by deletes and relocating updates, reclaimed by
+    /// `compact`.
+    pub dead_bytes: usize,
+}
+
 pub struct Page {
-    id: u16,
+    id: AtomicU16,
     header_offsets: PageHeaderOffsets,
     page_size: usize,
+    endianness: Endianness,
+    text_encoding: TextEncoding,
     buffer: Arc<RwLock<Vec<u8>>>,
 }
 
 impl Page {
+    /// Wraps `buffer` in the byte order its header records, or creates an
+    /// empty little-endian page when no buffer is given.
     pub fn new(id: u16, page_size: usize, buffer: Option<Vec<u8>>) -> Self {
-        let (buffer, write_metadata) = if let Some(buffer) = buffer {
-            (buffer, false)
-        } else {
-            (vec![0u8; page_size], true)
+        Self::build(
+            id,
+            page_size,
+            buffer,
+            Endianness::default(),
+            TextEncoding::default(),
+        )
+    }
+
+    /// Creates an empty page that stores every value in `endianness`.
+    pub fn with_endianness(id: u16, page_size: usize, endianness: Endianness) -> Self {
+        Self::build(id, page_size, None, endianness, TextEncoding::default())
+    }
+
+    /// Creates an empty page that stores `Text` and `LargeText` in
+    /// `text_encoding`.
+    pub fn with_text_encoding(id: u16, page_size: usize, text_encoding: TextEncoding) -> Self {
+        Self::build(id, page_size, None, Endianness::default(), text_encoding)
+    }
+
+    /// Creates an empty page flagged as holding one chunk of an overflowed
+    /// field rather than ordinary rows.
+    pub fn new_overflow(id: u16, page_size: usize) -> Self {
+        let page = Self::new(id, page_size, None);
+        if let Some(flags) = page.header_offsets.flags {
+            page.get_write_buffer()[flags] |= FLAG_OVERFLOW;
+        }
+        page
+    }
+
+    /// Creates an empty page that keeps a bloom filter over field
+    /// `field_index` of every row written to it, so `might_contain` can rule
+    /// a value out without decoding any rows. The filter is stored at the end
+    /// of the page, leaving less room for rows. A page too small to hold any
+    /// filter bits is created without a filter.
+    pub fn with_bloom_filter(id: u16, page_size: usize, field_index: u16) -> Self {
+        let page = Self::new(id, page_size, None);
+        let Some(region) = filter_start(page_size, page.header_offsets.end_headers) else {
+            return page;
+        };
+        let mut buffer = page.get_write_buffer();
+        buffer[page.header_offsets.flags.unwrap()] |= FLAG_BLOOM;
+        buffer[region..region + 2]
+            .copy_from_slice(&page.endianness.order(field_index.to_le_bytes()));
+        drop(buffer);
+        let mut page_header = page.read_metadata();
+        page_header.higher = region as u16;
+        page.write_metadata(&page_header);
+        page
+    }
+
+    // Start of the reserved bloom filter region, if the page has one.
+    fn filter_region(&self, buffer: &[u8]) -> Option<usize> {
+        let flags = buffer[self.header_offsets.flags?];
+        if flags & FLAG_BLOOM == 0 {
+            return None;
+        }
+        filter_start(self.page_size, self.header_offsets.end_headers)
+    }
+
+    // Where row data ends: the page size, or the start of the filter region.
+    fn data_end(&self, buffer: &[u8]) -> usize {
+        self.filter_region(buffer).unwrap_or(self.page_size)
+    }
+
+    /// The field the page's bloom filter covers, if it has one.
+    pub fn bloom_field(&self) -> Option<usize> {
+        let buffer = self.get_buffer();
+        let mut offset = self.filter_region(&buffer)?;
+        Some(Self::read_u16(&buffer, &mut offset, self.endianness).ok()? as usize)
+    }
+
+    /// Whether a row with `value` at `field_index` may be on this page.
+    /// `false` is definite; `true` may be a false positive, and is always
+    /// returned when the page has no filter over `field_index`.
+    pub fn might_contain(&self, field_index: usize, value: &DataField) -> bool {
+        if self.bloom_field() != Some(field_index) {
+            return true;
+        }
+        let buffer = self.get_buffer();
+        let region = self.filter_region(&buffer).unwrap();
+        match Self::encode_row(std::slice::from_ref(value), Endianness::Little) {
+            Ok(key) => bloom::might_contain(&buffer[region + 2..], &key),
+            Err(_) => false,
+        }
+    }
+
+    // Adds the filtered field of a newly written row to the bloom filter. A
+    // field spilled to overflow pages is written as a reference whose value
+    // the filter cannot know, so it saturates the filter instead.
+    fn add_to_filter(&self, buffer: &mut [u8], data_fields: &[DataField]) {
+        let Some(region) = self.filter_region(buffer) else {
+            return;
         };
+        let field_index = u16::from_le_bytes(
+            self.endianness
+                .order(buffer[region..region + 2].try_into().unwrap()),
+        );
+        let bits = &mut buffer[region + 2..];
+        match data_fields.get(field_index as usize) {
+            Some(DataField::Overflow { .. }) => bits.fill(0xFF),
+            Some(value) => {
+                if let Ok(key) = Self::encode_row(std::slice::from_ref(value), Endianness::Little) {
+                    bloom::insert(bits, &key);
+                }
+            }
+            None => {}
+        }
+    }
+
+    pub fn is_overflow(&self) -> bool {
+        self.header_offsets
+            .flags
+            .is_some_and(|flags| self.get_buffer()[flags] & FLAG_OVERFLOW != 0)
+    }
+
+    // The header layout, byte order and text encoding `buffer` was written
+    // with. Unknown flags are reported by `check_format`. Layouts without a
+    // flags byte were always little-endian utf-8.
+    fn layout(buffer: &[u8]) -> (PageHeaderOffsets, Endianness, TextEncoding) {
+        let header_offsets = PageHeaderOffsets::detect(buffer);
+        let flags = header_offsets
+            .flags
+            .and_then(|flags| buffer.get(flags))
+            .copied()
+            .unwrap_or(0);
+        (
+            header_offsets,
+            Endianness::from_flags(flags),
+            TextEncoding::from_flags(flags),
+        )
+    }
+
+    fn build(
+        id: u16,
+        page_size: usize,
+        buffer: Option<Vec<u8>>,
+        endianness: Endianness,
+        text_encoding: TextEncoding,
+    ) -> Self {
+        let (buffer, header_offsets, endianness, text_encoding, write_metadata) =
+            if let Some(buffer) = buffer {
+                let (header_offsets, endianness, text_encoding) = Self::layout(&buffer);
+                (buffer, header_offsets, endianness, text_encoding, false)
+            } else {
+                let header_offsets = PageHeaderOffsets::current();
+                (
+                    vec![0u8; page_size],
+                    header_offsets,
+                    endianness,
+                    text_encoding,
+                    true,
+                )
+            };
         let page = Self {
-            id,
-            header_offsets: PageHeaderOffsets {
-                id: (0, 2),
-                lower: (2, 4),
-                higher: (4, 6),
-                end_headers: 6,
-            },
+            id: AtomicU16::new(id),
+            header_offsets,
             page_size,
+            endianness,
+            text_encoding,
             buffer: Arc::new(RwLock::new(buffer)),
         };
         if write_metadata {
+            let mut buffer = page.get_write_buffer();
+            let header_offsets = &page.header_offsets;
+            let magic = header_offsets.magic.unwrap();
+            buffer[magic.0..magic.1].copy_from_slice(MAGIC);
+            buffer[header_offsets.version.unwrap()] = FORMAT_VERSION;
+            buffer[header_offsets.flags.unwrap()] = endianness.flags() | text_encoding.flags();
+            let next_page = header_offsets.next_page.unwrap();
+            buffer[next_page.0..next_page.1]
+                .copy_from_slice(&endianness.order(NO_NEXT_PAGE.to_le_bytes()));
+            drop(buffer);
             page.write_metadata(&PageHeader {
                 id,
                 lower: page.header_offsets.end_headers,
@@ -65,148 +816,3221 @@ impl Page {
         page
     }
 
-    pub fn get_buffer(&self) -> RwLockReadGuard<Vec<u8>> {
+    pub fn id(&self) -> u16 {
+        self.id.load(atomic::Ordering::Relaxed)
+    }
+
+    /// The page that follows this one in its chain, if any.
+    pub fn next(&self) -> Option<u16> {
+        self.header()
+            .next_page
+            .filter(|&next_page| next_page != NO_NEXT_PAGE)
+    }
+
+    /// Links the page to `next_page`, or ends its chain with `None`. Pages
+    /// before version 4 have no room for the link and are rejected.
+    pub fn set_next(&self, next_page: Option<u16>) -> Result<(), PageError> {
+        let offsets = self
+            .header_offsets
+            .next_page
+            .ok_or(PageError::UnsupportedVersion(
+                self.header_offsets.format_version,
+            ))?;
+        let next_page = next_page.unwrap_or(NO_NEXT_PAGE);
+        self.get_write_buffer()[offsets.0..offsets.1]
+            .copy_from_slice(&self.endianness.order(next_page.to_le_bytes()));
+        Ok(())
+    }
+
+    /// Renumbers the page, updating the id recorded in its header as well.
+    pub fn set_id(&self, id: u16) {
+        let mut buffer = self.get_write_buffer();
+        let mut page_header = self.read_header(&buffer);
+        page_header.id = id;
+        self.write_header(&mut buffer, &page_header);
+        self.id.store(id, atomic::Ordering::Relaxed);
+    }
+
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    pub fn text_encoding(&self) -> TextEncoding {
+        self.text_encoding
+    }
+
+    pub fn get_buffer(&self) -> RwLockReadGuard<'_, Vec<u8>> {
         self.buffer.read().unwrap()
     }
 
-    fn get_write_buffer(&self) -> RwLockWriteGuard<Vec<u8>> {
+    /// Runs `f` on the page bytes without copying them. The read lock is held
+    /// for as long as `f` runs, so writers to this page wait until it returns.
+    pub fn with_buffer<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        f(&self.get_buffer())
+    }
+
+    /// Copies the page under a single read lock. Reads on the snapshot then
+    /// decode without any lock on this page, so they never hold up writers.
+    pub fn snapshot(&self) -> PageSnapshot {
+        PageSnapshot {
+            page: Page {
+                id: AtomicU16::new(self.id()),
+                header_offsets: self.header_offsets.clone(),
+                page_size: self.page_size,
+                endianness: self.endianness,
+                text_encoding: self.text_encoding,
+                buffer: Arc::new(RwLock::new(self.get_buffer().clone())),
+            },
+        }
+    }
+
+    fn get_write_buffer(&self) -> RwLockWriteGuard<'_, Vec<u8>> {
         self.buffer.write().unwrap()
     }
 
     fn write_metadata(&self, page_header: &PageHeader) {
         let mut buffer = self.get_write_buffer();
+        self.write_header(&mut buffer, page_header);
+    }
+
+    fn write_header(&self, buffer: &mut [u8], page_header: &PageHeader) {
         let id = page_header.id;
         let header_offsets = &self.header_offsets;
-        buffer[header_offsets.id.0..header_offsets.id.1].copy_from_slice(&id.to_le_bytes());
+        buffer[header_offsets.id.0..header_offsets.id.1]
+            .copy_from_slice(&self.endianness.order(id.to_le_bytes()));
         let lower = page_header.lower;
         buffer[header_offsets.lower.0..header_offsets.lower.1]
-            .copy_from_slice(&lower.to_le_bytes());
+            .copy_from_slice(&self.endianness.order(lower.to_le_bytes()));
         let higher = page_header.higher;
         buffer[header_offsets.higher.0..header_offsets.higher.1]
-            .copy_from_slice(&higher.to_le_bytes());
+            .copy_from_slice(&self.endianness.order(higher.to_le_bytes()));
     }
 
     fn read_metadata(&self) -> PageHeader {
-        let buffer = self.get_buffer();
+        self.read_header(&self.get_buffer())
+    }
+
+    fn read_header(&self, buffer: &[u8]) -> PageHeader {
         let header_offsets = &self.header_offsets;
         let id = u16::from_le_bytes(
-            buffer[header_offsets.id.0..header_offsets.id.1]
-                .try_into()
-                .unwrap(),
+            self.endianness.order(
+                buffer[header_offsets.id.0..header_offsets.id.1]
+                    .try_into()
+                    .unwrap(),
+            ),
         );
         let lower = u16::from_le_bytes(
-            buffer[header_offsets.lower.0..header_offsets.lower.1]
-                .try_into()
-                .unwrap(),
+            self.endianness.order(
+                buffer[header_offsets.lower.0..header_offsets.lower.1]
+                    .try_into()
+                    .unwrap(),
+            ),
         );
         let higher = u16::from_le_bytes(
-            buffer[header_offsets.higher.0..header_offsets.higher.1]
-                .try_into()
-                .unwrap(),
+            self.endianness.order(
+                buffer[header_offsets.higher.0..header_offsets.higher.1]
+                    .try_into()
+                    .unwrap(),
+            ),
         );
         PageHeader { id, lower, higher }
     }
 
-    pub fn write(&self, data_fields: &Vec<DataField>) {
-        let mut row = Vec::new();
-        let mut data = Vec::new();
-        let mut data_len = 0 as u16;
-        row.extend_from_slice(&(data_fields.len() as u16).to_le_bytes());
-        data_len += 2;
-        for field in data_fields {
-            row.extend_from_slice(&field.to_int().to_le_bytes());
-            data_len += 2;
-            match field {
-                DataField::Text(text) => {
-                    let text_to_bytes = text.as_bytes();
-                    let text_len = text_to_bytes.len() as u16;
-                    data.extend_from_slice(&text_len.to_le_bytes());
-                    data_len += 2;
-                    data.extend_from_slice(text_to_bytes);
-                    data_len += text_len;
-                }
-                DataField::Integer(int) => {
-                    data.extend_from_slice(&int.to_le_bytes());
-                    data_len += 2;
-                }
-            }
-        }
-        row.extend_from_slice(&data);
-        let mut page_header = self.read_metadata();
+    fn compute_checksum(buffer: &[u8], checksum_offsets: (usize, usize)) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(&buffer[..checksum_offsets.0]);
+        crc.update(&buffer[checksum_offsets.1..]);
+        crc.finish()
+    }
+
+    /// Checks that the buffer starts with this crate's magic bytes and was
+    /// written in a format version this build can read.
+    pub fn check_format(&self) -> Result<(), PageError> {
+        self.view(&self.get_buffer()).check_format()
+    }
+
+    /// Stores the page's checksum in its header. Version 1 pages have nowhere
+    /// to store one and are left as is.
+    pub fn seal(&self) {
+        let Some(checksum_offsets) = self.header_offsets.checksum else {
+            return;
+        };
         let mut buffer = self.get_write_buffer();
-        page_header.higher -= data_len;
-        let data_offset = page_header.higher;
-        buffer[data_offset as usize..(data_offset + data_len) as usize].copy_from_slice(&row);
-        let pointer_offset = page_header.lower;
-        buffer[pointer_offset as usize..pointer_offset as usize + 2]
-            .copy_from_slice(&data_offset.to_le_bytes());
-        page_header.lower += 2;
-        drop(buffer);
-        self.write_metadata(&page_header);
+        let checksum = Self::compute_checksum(&buffer, checksum_offsets);
+        buffer[checksum_offsets.0..checksum_offsets.1]
+            .copy_from_slice(&self.endianness.order(checksum.to_le_bytes()));
     }
 
-    pub fn read(&self) -> Vec<Vec<DataField>> {
-        let mut pointers = Vec::new();
-        let page_header = self.read_metadata();
-        let mut offset = self.header_offsets.end_headers as usize;
+    pub fn verify(&self) -> Result<(), PageError> {
+        self.view(&self.get_buffer()).verify()
+    }
+
+    fn view<'a>(&self, buffer: &'a [u8]) -> PageView<'a> {
+        PageView {
+            buffer,
+            header_offsets: self.header_offsets.clone(),
+            page_size: self.page_size,
+            endianness: self.endianness,
+            text_encoding: self.text_encoding,
+        }
+    }
+
+    /// Seals the page and serializes it with the row data compressed. The
+    /// header and slot pointers are kept as they are so the layout can still
+    /// be parsed, followed by the compressed length as a `u32` and the
+    /// compressed bytes of the data region below `higher`. Headers with a
+    /// flags byte are marked compressed, which `is_compressed` detects.
+    pub fn to_compressed(&self) -> Vec<u8> {
+        self.seal();
         let buffer = self.get_buffer();
-        while offset <= (page_header.lower - 2) as usize {
-            pointers.push(u16::from_le_bytes(
-                buffer[offset..offset + 2].try_into().unwrap(),
-            ));
-            offset += 2;
+        let page_header = self.read_header(&buffer);
+        let compressed = compression::compress(&buffer[page_header.higher as usize..]);
+        let mut bytes = buffer[..page_header.lower as usize].to_vec();
+        if let Some(flags) = self.header_offsets.flags {
+            bytes[flags] |= FLAG_COMPRESSED;
         }
-        let mut rows = Vec::new();
-        for pointer in pointers {
-            let mut row = Vec::new();
-            let mut offset = pointer as usize;
-            let mut num_of_fields =
-                u16::from_le_bytes(buffer[offset..offset + 2].try_into().unwrap());
-            offset += 2;
-            let mut datatype_nums = Vec::new();
-            while num_of_fields != 0 {
-                let datatype_num =
-                    u16::from_le_bytes(buffer[offset..offset + 2].try_into().unwrap());
-                datatype_nums.push(datatype_num);
-                offset += 2;
-                num_of_fields -= 1;
-            }
-            for datatype_num in datatype_nums {
-                match datatype_num {
-                    1 => {
-                        let integer =
-                            u16::from_le_bytes(buffer[offset..offset + 2].try_into().unwrap());
-                        row.push(DataField::Integer(integer));
-                        offset += 2;
-                    }
-                    2 => {
-                        let text_length =
-                            u16::from_le_bytes(buffer[offset..offset + 2].try_into().unwrap())
-                                as usize;
-                        offset += 2;
-                        let text = String::from_utf8(buffer[offset..offset + text_length].to_vec())
-                            .unwrap();
-                        row.push(DataField::Text(text));
-                        offset += text_length;
-                    }
-                    _ => panic!("invalid number"),
-                }
-            }
-            rows.push(row);
+        bytes.extend_from_slice(
+            &self
+                .endianness
+                .order((compressed.len() as u32).to_le_bytes()),
+        );
+        bytes.extend_from_slice(&compressed);
+        bytes
+    }
+
+    /// Rebuilds a page serialized by `to_compressed`, checking its format and
+    /// checksum.
+    pub fn from_compressed(id: u16, page_size: usize, bytes: &[u8]) -> Result<Page, PageError> {
+        Self::read_compressed(id, page_size, bytes).map(|(page, _)| page)
+    }
+
+    /// Whether `bytes` start with a page serialized by `to_compressed`.
+    pub fn is_compressed(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+            && PageHeaderOffsets::detect(bytes)
+                .flags
+                .and_then(|flags| bytes.get(flags))
+                .is_some_and(|&flags| flags & FLAG_COMPRESSED != 0)
+    }
+
+    // Like `from_compressed`, but also returns how many bytes the page took,
+    // so pages serialized back to back can be read one after another.
+    pub(crate) fn read_compressed(
+        id: u16,
+        page_size: usize,
+        bytes: &[u8],
+    ) -> Result<(Page, usize), PageError> {
+        let header_offsets = PageHeaderOffsets::detect(bytes);
+        let end_headers = header_offsets.end_headers as usize;
+        let mut buffer = vec![0u8; page_size];
+        let mut offset = 0;
+        buffer[..end_headers].copy_from_slice(Self::take(bytes, &mut offset, end_headers)?);
+        if let Some(flags) = header_offsets.flags {
+            buffer[flags] &= !FLAG_COMPRESSED;
         }
-        rows
+        let page = Page::new(id, page_size, Some(buffer));
+        page.check_format()?;
+        let page_header = page.read_metadata();
+        let (lower, higher) = (page_header.lower as usize, page_header.higher as usize);
+        if lower < end_headers || lower > higher || higher > page_size {
+            return Err(PageError::CorruptPage {
+                offset: end_headers,
+            });
+        }
+        let mut buffer = page.get_write_buffer();
+        buffer[end_headers..lower].copy_from_slice(Self::take(
+            bytes,
+            &mut offset,
+            lower - end_headers,
+        )?);
+        let compressed_len =
+            u32::from_le_bytes(page.endianness.order(Self::take_array(bytes, &mut offset)?))
+                as usize;
+        let data_offset = offset;
+        let data = compression::decompress(Self::take(bytes, &mut offset, compressed_len)?)
+            .filter(|data| data.len() == page_size - higher)
+            .ok_or(PageError::CorruptPage {
+                offset: data_offset,
+            })?;
+        buffer[higher..].copy_from_slice(&data);
+        drop(buffer);
+        page.verify()?;
+        Ok((page, offset))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Seals the page and returns a copy of its bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.seal();
+        self.get_buffer().clone()
+    }
+
+    /// Rebuilds a page serialized by `to_bytes`, checking its length, format,
+    /// header offsets and checksum.
+    pub fn from_bytes(id: u16, page_size: usize, bytes: Vec<u8>) -> Result<Page, PageError> {
+        if bytes.len() != page_size {
+            return Err(PageError::WrongPageSize {
+                expected: page_size,
+                found: bytes.len(),
+            });
+        }
+        let page = Page::new(id, page_size, Some(bytes));
+        page.check_format()?;
+        let end_headers = page.header_offsets.end_headers as usize;
+        let page_header = page.read_metadata();
+        let (lower, higher) = (page_header.lower as usize, page_header.higher as usize);
+        if lower < end_headers || lower > higher || higher > page_size {
+            return Err(PageError::CorruptPage {
+                offset: end_headers,
+            });
+        }
+        page.verify()?;
+        Ok(page)
+    }
+
+    pub fn free_space(&self) -> usize {
+        let page_header = self.read_metadata();
+        (page_header.higher - page_header.lower) as usize
+    }
+
+    /// Roughly how many more rows of `avg_row_size` bytes (as measured by
+    /// `row_size`) fit, each with a new slot pointer. This is only an
+    /// estimate: actual rows vary in size, reused deleted slots need no new
+    /// pointer, and dead bytes only become usable after `compact`.
+    pub fn estimated_remaining_rows(&self, avg_row_size: usize) -> usize {
+        self.free_space() / (avg_row_size + 2)
+    }
+
+    /// Whether `write` would succeed for this row, counting the 2-byte slot
+    /// pointer unless a deleted slot can be reused.
+    pub fn can_fit(&self, data_fields: &[DataField]) -> bool {
+        let buffer = self.get_buffer();
+        let page_header = self.read_header(&buffer);
+        let pointer_len = match self.free_slot(&buffer, page_header.lower) {
+            Some(_) => 0,
+            None => 2,
+        };
+        row_size(data_fields) + pointer_len
+            <= page_header.higher.saturating_sub(page_header.lower) as usize
+    }
+
+    pub(crate) fn encode_row(
+        data_fields: &[DataField],
+        endianness: Endianness,
+    ) -> Result<Vec<u8>, PageError> {
+        Self::encode_row_as(data_fields, endianness, TextEncoding::Utf8)
+    }
+
+    fn encode_row_as(
+        data_fields: &[DataField],
+        endianness: Endianness,
+        text_encoding: TextEncoding,
+    ) -> Result<Vec<u8>, PageError> {
+        if array_depth(data_fields) > MAX_ARRAY_DEPTH {
+            return Err(PageError::ArrayTooDeep);
+        }
+        Self::encode_fields(data_fields, endianness, text_encoding)
+    }
+
+    fn encode_fields(
+        data_fields: &[DataField],
+        endianness: Endianness,
+        text_encoding: TextEncoding,
+    ) -> Result<Vec<u8>, PageError> {
+        if data_fields.len() > MAX_FIELDS {
+            return Err(PageError::TooManyFields {
+                count: data_fields.len(),
+            });
+        }
+        let mut row = Vec::with_capacity(row_size(data_fields));
+        let mut data = Vec::new();
+        row.extend_from_slice(&endianness.order((data_fields.len() as u16).to_le_bytes()));
+        for field in data_fields {
+            row.extend_from_slice(&endianness.order(field.to_int().to_le_bytes()));
+            match field {
+                DataField::Text(text) => {
+                    let text_to_bytes = &text_encoding.encode(text)?;
+                    let text_len =
+                        u16::try_from(text_to_bytes.len()).map_err(|_| PageError::TextTooLong {
+                            len: text_to_bytes.len(),
+                        })?;
+                    data.extend_from_slice(&endianness.order(text_len.to_le_bytes()));
+                    data.extend_from_slice(text_to_bytes);
+                }
+                DataField::Integer(int) => {
+                    data.extend_from_slice(&endianness.order(int.to_le_bytes()));
+                }
+                DataField::Int32(int) => {
+                    data.extend_from_slice(&endianness.order(int.to_le_bytes()));
+                }
+                DataField::Int64(int) => {
+                    data.extend_from_slice(&endianness.order(int.to_le_bytes()));
+                }
+                DataField::Float64(float) => {
+                    data.extend_from_slice(&endianness.order(float.to_le_bytes()));
+                }
+                DataField::Null => {}
+                DataField::Bool(boolean) => {
+                    data.push(*boolean as u8);
+                }
+                DataField::Bytes(bytes) => {
+                    data.extend_from_slice(&endianness.order(bytes_len(bytes)?.to_le_bytes()));
+                    data.extend_from_slice(bytes);
+                }
+                DataField::LargeText(text) => {
+                    let text_to_bytes = &text_encoding.encode(text)?;
+                    data.extend_from_slice(
+                        &endianness.order((text_to_bytes.len() as u32).to_le_bytes()),
+                    );
+                    data.extend_from_slice(text_to_bytes);
+                }
+                DataField::UInt32(int) => {
+                    data.extend_from_slice(&endianness.order(int.to_le_bytes()));
+                }
+                DataField::Timestamp(micros) => {
+                    data.extend_from_slice(&endianness.order(micros.to_le_bytes()));
+                }
+                DataField::Uuid(bytes) => {
+                    data.extend_from_slice(bytes);
+                }
+                DataField::VarInt(value) => {
+                    let mut value = *value;
+                    while value >= 0x80 {
+                        data.push((value as u8) | 0x80);
+                        value >>= 7;
+                    }
+                    data.push(value as u8);
+                }
+                DataField::Overflow {
+                    datatype_num,
+                    page_id,
+                    len,
+                } => {
+                    data.extend_from_slice(&endianness.order(datatype_num.to_le_bytes()));
+                    data.extend_from_slice(&endianness.order(page_id.to_le_bytes()));
+                    data.extend_from_slice(&endianness.order(len.to_le_bytes()));
+                }
+                DataField::Decimal { mantissa, scale } => {
+                    data.extend_from_slice(&endianness.order(mantissa.to_le_bytes()));
+                    data.push(*scale);
+                }
+                DataField::Char(c) => {
+                    data.extend_from_slice(&endianness.order((*c as u32).to_le_bytes()));
+                }
+                DataField::Array(elements) => {
+                    data.extend_from_slice(&Self::encode_fields(
+                        elements,
+                        endianness,
+                        text_encoding,
+                    )?);
+                }
+                DataField::CheckedBytes(bytes) => {
+                    data.extend_from_slice(&endianness.order(bytes_len(bytes)?.to_le_bytes()));
+                    data.extend_from_slice(&endianness.order(crc32(bytes).to_le_bytes()));
+                    data.extend_from_slice(bytes);
+                }
+                DataField::Json(value) => {
+                    if value.depth() > MAX_DEPTH {
+                        return Err(PageError::JsonTooDeep);
+                    }
+                    let text = value.to_string();
+                    data.extend_from_slice(&endianness.order((text.len() as u32).to_le_bytes()));
+                    data.extend_from_slice(text.as_bytes());
+                }
+                DataField::Int16(int) => {
+                    data.extend_from_slice(&endianness.order(int.to_le_bytes()));
+                }
+            }
+        }
+        row.extend_from_slice(&data);
+        Ok(row)
+    }
+
+    // Returns the `len` bytes at `offset` and advances past them, or
+    // `CorruptPage` if they would run past the end of the page.
+    fn take<'b>(buffer: &'b [u8], offset: &mut usize, len: usize) -> Result<&'b [u8], PageError> {
+        let start = *offset;
+        let end = start
+            .checked_add(len)
+            .filter(|&end| end <= buffer.len())
+            .ok_or(PageError::CorruptPage { offset: start })?;
+        *offset = end;
+        Ok(&buffer[start..end])
+    }
+
+    fn take_array<const N: usize>(buffer: &[u8], offset: &mut usize) -> Result<[u8; N], PageError> {
+        Ok(Self::take(buffer, offset, N)?.try_into().unwrap())
+    }
+
+    fn read_u16(
+        buffer: &[u8],
+        offset: &mut usize,
+        endianness: Endianness,
+    ) -> Result<u16, PageError> {
+        Ok(u16::from_le_bytes(
+            endianness.order(Self::take_array(buffer, offset)?),
+        ))
+    }
+
+    /// Encodes a single field's payload, as stored after its type tag, for
+    /// spilling to overflow pages. `Bytes` and `CheckedBytes` take a u32
+    /// length there instead of a u16, since overflowed blobs are often
+    /// longer than 64 KiB.
+    pub(crate) fn encode_payload(field: &DataField) -> Result<Vec<u8>, PageError> {
+        Ok(match field {
+            DataField::Bytes(bytes) => [&(bytes.len() as u32).to_le_bytes()[..], bytes].concat(),
+            DataField::CheckedBytes(bytes) => [
+                &(bytes.len() as u32).to_le_bytes()[..],
+                &crc32(bytes).to_le_bytes(),
+                bytes,
+            ]
+            .concat(),
+            _ => Self::encode_row(std::slice::from_ref(field), Endianness::Little)?[4..].to_vec(),
+        })
+    }
+
+    pub(crate) fn decode_payload(
+        datatype_num: u16,
+        payload: &[u8],
+        first_page_id: u16,
+    ) -> Result<DataField, PageError> {
+        let offset = &mut 0;
+        match datatype_num {
+            8 => {
+                let bytes_length = u32::from_le_bytes(Self::take_array(payload, offset)?) as usize;
+                return Ok(DataField::Bytes(
+                    Self::take(payload, offset, bytes_length)?.to_vec(),
+                ));
+            }
+            18 => {
+                let bytes_length = u32::from_le_bytes(Self::take_array(payload, offset)?) as usize;
+                let stored = u32::from_le_bytes(Self::take_array(payload, offset)?);
+                let bytes = Self::take(payload, offset, bytes_length)?;
+                if crc32(bytes) != stored {
+                    return Err(PageError::OverflowChecksumMismatch {
+                        page_id: first_page_id,
+                    });
+                }
+                return Ok(DataField::CheckedBytes(bytes.to_vec()));
+            }
+            _ => {}
+        }
+        Self::decode_field(
+            payload,
+            &mut 0,
+            datatype_num,
+            0,
+            Endianness::Little,
+            TextEncoding::Utf8,
+        )
+    }
+
+    fn read_varint(buffer: &[u8], offset: &mut usize) -> Result<u64, PageError> {
+        let start = *offset;
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = Self::take(buffer, offset, 1)?[0];
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(PageError::CorruptPage { offset: start })
+    }
+
+    fn read_datatype_nums(
+        buffer: &[u8],
+        offset: &mut usize,
+        endianness: Endianness,
+    ) -> Result<Vec<u16>, PageError> {
+        let num_of_fields = Self::read_u16(buffer, offset, endianness)?;
+        if num_of_fields & PACKED_INTS != 0 {
+            return Ok(vec![1; (num_of_fields & !PACKED_INTS) as usize]);
+        }
+        (0..num_of_fields)
+            .map(|_| Self::read_u16(buffer, offset, endianness))
+            .collect()
+    }
+
+    // Decodes a field count, its tags and then each payload, the layout
+    // shared by rows and `Array` payloads. `depth` counts the arrays the
+    // fields are nested in.
+    fn decode_fields(
+        buffer: &[u8],
+        offset: &mut usize,
+        slot: usize,
+        endianness: Endianness,
+        text_encoding: TextEncoding,
+        depth: usize,
+    ) -> Result<Vec<DataField>, PageError> {
+        Self::read_datatype_nums(buffer, offset, endianness)?
+            .into_iter()
+            .map(|datatype_num| {
+                Self::decode_nested_field(
+                    buffer,
+                    offset,
+                    datatype_num,
+                    slot,
+                    endianness,
+                    text_encoding,
+                    depth,
+                )
+            })
+            .collect()
+    }
+
+    fn decode_field(
+        buffer: &[u8],
+        offset: &mut usize,
+        datatype_num: u16,
+        slot: usize,
+        endianness: Endianness,
+        text_encoding: TextEncoding,
+    ) -> Result<DataField, PageError> {
+        Self::decode_nested_field(
+            buffer,
+            offset,
+            datatype_num,
+            slot,
+            endianness,
+            text_encoding,
+            0,
+        )
+    }
+
+    fn decode_nested_field(
+        buffer: &[u8],
+        offset: &mut usize,
+        datatype_num: u16,
+        slot: usize,
+        endianness: Endianness,
+        text_encoding: TextEncoding,
+        depth: usize,
+    ) -> Result<DataField, PageError> {
+        let field = match datatype_num {
+            1 => DataField::Integer(u16::from_le_bytes(
+                endianness.order(Self::take_array(buffer, offset)?),
+            )),
+            2 => {
+                let text_length = Self::read_u16(buffer, offset, endianness)? as usize;
+                let text = text_encoding.decode(Self::take(buffer, offset, text_length)?, slot)?;
+                DataField::Text(text)
+            }
+            3 => DataField::Int32(i32::from_le_bytes(
+                endianness.order(Self::take_array(buffer, offset)?),
+            )),
+            4 => DataField::Int64(i64::from_le_bytes(
+                endianness.order(Self::take_array(buffer, offset)?),
+            )),
+            5 => DataField::Float64(f64::from_le_bytes(
+                endianness.order(Self::take_array(buffer, offset)?),
+            )),
+            6 => DataField::Null,
+            7 => DataField::Bool(Self::take(buffer, offset, 1)?[0] != 0),
+            8 => {
+                let bytes_length = Self::read_u16(buffer, offset, endianness)? as usize;
+                DataField::Bytes(Self::take(buffer, offset, bytes_length)?.to_vec())
+            }
+            9 => {
+                let text_length =
+                    u32::from_le_bytes(endianness.order(Self::take_array(buffer, offset)?))
+                        as usize;
+                let text = text_encoding.decode(Self::take(buffer, offset, text_length)?, slot)?;
+                DataField::LargeText(text)
+            }
+            10 => DataField::UInt32(u32::from_le_bytes(
+                endianness.order(Self::take_array(buffer, offset)?),
+            )),
+            11 => DataField::Timestamp(i64::from_le_bytes(
+                endianness.order(Self::take_array(buffer, offset)?),
+            )),
+            12 => DataField::Uuid(Self::take_array(buffer, offset)?),
+            13 => DataField::VarInt(Self::read_varint(buffer, offset)?),
+            14 => DataField::Overflow {
+                datatype_num: Self::read_u16(buffer, offset, endianness)?,
+                page_id: Self::read_u16(buffer, offset, endianness)?,
+                len: u32::from_le_bytes(endianness.order(Self::take_array(buffer, offset)?)),
+            },
+            15 => DataField::Decimal {
+                mantissa: i64::from_le_bytes(endianness.order(Self::take_array(buffer, offset)?)),
+                scale: Self::take(buffer, offset, 1)?[0],
+            },
+            16 => {
+                let value = u32::from_le_bytes(endianness.order(Self::take_array(buffer, offset)?));
+                DataField::Char(
+                    char::from_u32(value).ok_or(PageError::InvalidChar { slot, value })?,
+                )
+            }
+            17 if depth == MAX_ARRAY_DEPTH => {
+                return Err(PageError::CorruptPage { offset: *offset })
+            }
+            17 => DataField::Array(Self::decode_fields(
+                buffer,
+                offset,
+                slot,
+                endianness,
+                text_encoding,
+                depth + 1,
+            )?),
+            18 => {
+                let bytes_length = Self::read_u16(buffer, offset, endianness)? as usize;
+                let stored =
+                    u32::from_le_bytes(endianness.order(Self::take_array(buffer, offset)?));
+                let bytes = Self::take(buffer, offset, bytes_length)?;
+                if crc32(bytes) != stored {
+                    return Err(PageError::BlobChecksumMismatch { slot });
+                }
+                DataField::CheckedBytes(bytes.to_vec())
+            }
+            19 => {
+                let text_length =
+                    u32::from_le_bytes(endianness.order(Self::take_array(buffer, offset)?))
+                        as usize;
+                let text = std::str::from_utf8(Self::take(buffer, offset, text_length)?)
+                    .map_err(|_| PageError::InvalidUtf8 { slot })?;
+                DataField::Json(
+                    JsonValue::parse(text).map_err(|_| PageError::InvalidJson { slot })?,
+                )
+            }
+            20 => DataField::Int16(i16::from_le_bytes(
+                endianness.order(Self::take_array(buffer, offset)?),
+            )),
+            _ => return Err(PageError::UnknownType(datatype_num)),
+        };
+        Ok(field)
+    }
+
+    // Advances past a field's payload without decoding it.
+    fn skip_field(
+        buffer: &[u8],
+        offset: &mut usize,
+        datatype_num: u16,
+        endianness: Endianness,
+    ) -> Result<(), PageError> {
+        Self::skip_nested_field(buffer, offset, datatype_num, endianness, 0)
+    }
+
+    fn skip_nested_field(
+        buffer: &[u8],
+        offset: &mut usize,
+        datatype_num: u16,
+        endianness: Endianness,
+        depth: usize,
+    ) -> Result<(), PageError> {
+        let length = match datatype_num {
+            1 => 2,
+            2 | 8 => Self::read_u16(buffer, offset, endianness)? as usize,
+            3 => 4,
+            4 | 5 => 8,
+            6 => 0,
+            7 => 1,
+            9 => u32::from_le_bytes(endianness.order(Self::take_array(buffer, offset)?)) as usize,
+            10 => 4,
+            11 => 8,
+            12 => 16,
+            13 => {
+                Self::read_varint(buffer, offset)?;
+                0
+            }
+            14 => 8,
+            15 => 9,
+            16 => 4,
+            17 if depth == MAX_ARRAY_DEPTH => {
+                return Err(PageError::CorruptPage { offset: *offset })
+            }
+            17 => {
+                for datatype_num in Self::read_datatype_nums(buffer, offset, endianness)? {
+                    Self::skip_nested_field(buffer, offset, datatype_num, endianness, depth + 1)?;
+                }
+                0
+            }
+            18 => Self::read_u16(buffer, offset, endianness)? as usize + 4,
+            19 => u32::from_le_bytes(endianness.order(Self::take_array(buffer, offset)?)) as usize,
+            20 => 2,
+            _ => return Err(PageError::UnknownType(datatype_num)),
+        };
+        Self::take(buffer, offset, length)?;
+        Ok(())
+    }
+
+    pub(crate) fn decode_row(
+        buffer: &[u8],
+        pointer: u16,
+        slot: usize,
+        endianness: Endianness,
+    ) -> Result<Vec<DataField>, PageError> {
+        Self::decode_row_as(buffer, pointer, slot, endianness, TextEncoding::Utf8)
+    }
+
+    fn decode_row_as(
+        buffer: &[u8],
+        pointer: u16,
+        slot: usize,
+        endianness: Endianness,
+        text_encoding: TextEncoding,
+    ) -> Result<Vec<DataField>, PageError> {
+        Self::decode_fields(
+            buffer,
+            &mut (pointer as usize),
+            slot,
+            endianness,
+            text_encoding,
+            0,
+        )
+    }
+
+    fn decode_projected_row(
+        buffer: &[u8],
+        pointer: u16,
+        slot: usize,
+        indices: &[usize],
+        endianness: Endianness,
+        text_encoding: TextEncoding,
+    ) -> Result<Vec<DataField>, PageError> {
+        let mut offset = pointer as usize;
+        let datatype_nums = Self::read_datatype_nums(buffer, &mut offset, endianness)?;
+        let mut fields = vec![None; datatype_nums.len()];
+        for (field_index, datatype_num) in datatype_nums.into_iter().enumerate() {
+            if indices.contains(&field_index) {
+                fields[field_index] = Some(Self::decode_field(
+                    buffer,
+                    &mut offset,
+                    datatype_num,
+                    slot,
+                    endianness,
+                    text_encoding,
+                )?);
+            } else {
+                Self::skip_field(buffer, &mut offset, datatype_num, endianness)?;
+            }
+        }
+        indices
+            .iter()
+            .map(|&field_index| {
+                fields
+                    .get(field_index)
+                    .cloned()
+                    .flatten()
+                    .ok_or(PageError::FieldOutOfRange(field_index))
+            })
+            .collect()
+    }
+
+    pub fn write(&self, data_fields: &[DataField]) -> Result<(), PageError> {
+        self.write_located(data_fields).map(|_| ())
+    }
+
+    /// Like `write`, but returns the slot the row was written to.
+    pub fn write_located(&self, data_fields: &[DataField]) -> Result<usize, PageError> {
+        let row = Self::encode_row_as(data_fields, self.endianness, self.text_encoding)?;
+        self.write_encoded(&row, data_fields)
+    }
+
+    // Appends `row`, already encoded from `data_fields`, and returns its slot.
+    fn write_encoded(&self, row: &[u8], data_fields: &[DataField]) -> Result<usize, PageError> {
+        let mut buffer = self.get_write_buffer();
+        let mut page_header = self.read_header(&buffer);
+        let slot = self.append_row(&mut buffer, &mut page_header, row)?;
+        self.add_to_filter(&mut buffer, data_fields);
+        self.write_header(&mut buffer, &page_header);
+        Ok(slot)
+    }
+
+    /// Writes the row and returns its slot, or returns `None` and leaves the
+    /// page untouched if it does not fit or cannot be encoded.
+    pub fn try_write(&self, data_fields: &[DataField]) -> Option<usize> {
+        self.write_located(data_fields).ok()
+    }
+
+    /// Writes a row of `Integer`s without a type tag per field, so it takes
+    /// two bytes less per field and `read_ints` can decode it without any
+    /// tag lookups. Other reads see an ordinary row of `Integer`s. `compact`
+    /// keeps the row packed, but anything that re-encodes it, such as
+    /// `update` or `rewrite`, stores it tagged again.
+    pub fn write_ints(&self, ints: &[u16]) -> Result<(), PageError> {
+        if ints.len() > MAX_FIELDS {
+            return Err(PageError::TooManyFields { count: ints.len() });
+        }
+        let mut row = Vec::with_capacity(2 + 2 * ints.len());
+        row.extend_from_slice(
+            &self
+                .endianness
+                .order((ints.len() as u16 | PACKED_INTS).to_le_bytes()),
+        );
+        for int in ints {
+            row.extend_from_slice(&self.endianness.order(int.to_le_bytes()));
+        }
+        let mut buffer = self.get_write_buffer();
+        let mut page_header = self.read_header(&buffer);
+        self.append_row(&mut buffer, &mut page_header, &row)?;
+        if self.filter_region(&buffer).is_some() {
+            let data_fields: Vec<_> = ints.iter().copied().map(DataField::Integer).collect();
+            self.add_to_filter(&mut buffer, &data_fields);
+        }
+        self.write_header(&mut buffer, &page_header);
+        Ok(())
+    }
+
+    /// Decodes every row as plain integers, taking the fast path for rows
+    /// from `write_ints`. A row with a field of any other type fails with
+    /// `TypeMismatch` naming the field's index.
+    pub fn read_ints(&self) -> Result<Vec<Vec<u16>>, PageError> {
+        let buffer = self.get_buffer();
+        let page_header = self.read_header(&buffer);
+        let mut rows = Vec::new();
+        for slot in 0..self.num_slots(page_header.lower) {
+            let pointer = self.pointer_at(&buffer, slot)?;
+            if pointer == TOMBSTONE {
+                continue;
+            }
+            let mut offset = pointer as usize;
+            let num_of_fields = Self::read_u16(&buffer, &mut offset, self.endianness)?;
+            if num_of_fields & PACKED_INTS == 0 {
+                offset = pointer as usize;
+                let datatype_nums =
+                    Self::read_datatype_nums(&buffer, &mut offset, self.endianness)?;
+                if let Some(field_index) = datatype_nums.iter().position(|&tag| tag != 1) {
+                    return Err(PageError::TypeMismatch {
+                        column: field_index.to_string(),
+                    });
+                }
+            }
+            let ints = (0..num_of_fields & !PACKED_INTS)
+                .map(|_| Self::read_u16(&buffer, &mut offset, self.endianness))
+                .collect::<Result<_, _>>()?;
+            rows.push(ints);
+        }
+        Ok(rows)
+    }
+
+    pub fn write_row(&self, schema: &Schema, values: &[DataField]) -> Result<(), PageError> {
+        schema.validate(values)?;
+        self.write(values)
+    }
+
+    /// Writes rows in order under a single lock until one does not fit, and
+    /// returns how many were written so the caller can continue elsewhere.
+    pub fn write_batch(&self, rows: &[Vec<DataField>]) -> Result<usize, PageError> {
+        self.write_batch_locked(&mut self.get_write_buffer(), rows)
+    }
+
+    /// Like `write_batch`, but returns `Ok(None)` without waiting if another
+    /// thread currently holds the page lock.
+    pub fn try_write_batch(&self, rows: &[Vec<DataField>]) -> Result<Option<usize>, PageError> {
+        let mut buffer = match self.buffer.try_write() {
+            Ok(buffer) => buffer,
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Poisoned(error)) => panic!("{}", error),
+        };
+        self.write_batch_locked(&mut buffer, rows).map(Some)
+    }
+
+    fn write_batch_locked(
+        &self,
+        buffer: &mut [u8],
+        rows: &[Vec<DataField>],
+    ) -> Result<usize, PageError> {
+        let mut page_header = self.read_header(buffer);
+        let mut written = 0;
+        let mut result = Ok(());
+        for data_fields in rows {
+            let appended = Self::encode_row_as(data_fields, self.endianness, self.text_encoding)
+                .and_then(|row| self.append_row(buffer, &mut page_header, &row));
+            match appended {
+                Ok(_) => {
+                    self.add_to_filter(buffer, data_fields);
+                    written += 1;
+                }
+                Err(PageError::PageFull) => break,
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                }
+            }
+        }
+        self.write_header(buffer, &page_header);
+        result.map(|()| written)
+    }
+
+    fn append_row(
+        &self,
+        buffer: &mut [u8],
+        page_header: &mut PageHeader,
+        row: &[u8],
+    ) -> Result<usize, PageError> {
+        let free_slot = self.free_slot(buffer, page_header.lower);
+        let (slot, lower) = match free_slot {
+            Some(slot) => (slot, page_header.lower),
+            None => (
+                self.num_slots(page_header.lower),
+                page_header.lower.saturating_add(2),
+            ),
+        };
+        let pointer_offset = self.header_offsets.end_headers as usize + slot * 2;
+        // The pointer array grows up and the row data grows down; checking
+        // both ends here means neither copy can land on the other region.
+        let data_offset = match (page_header.higher as usize).checked_sub(row.len()) {
+            Some(data_offset)
+                if pointer_offset + 2 <= data_offset
+                    && lower as usize <= data_offset
+                    && page_header.higher as usize <= buffer.len() =>
+            {
+                data_offset
+            }
+            _ => return Err(PageError::PageFull),
+        };
+        page_header.lower = lower;
+        page_header.higher = data_offset as u16;
+        buffer[data_offset..data_offset + row.len()].copy_from_slice(row);
+        buffer[pointer_offset..pointer_offset + 2]
+            .copy_from_slice(&self.endianness.order(page_header.higher.to_le_bytes()));
+        Ok(slot)
+    }
+
+    // The first tombstoned slot, which `write` reuses before growing the
+    // pointer array.
+    fn free_slot(&self, buffer: &[u8], lower: u16) -> Option<usize> {
+        (0..self.num_slots(lower)).find(|&slot| self.pointer_at(buffer, slot) == Ok(TOMBSTONE))
+    }
+
+    /// Slot the next `write` will store its row in.
+    pub fn next_slot(&self) -> usize {
+        let buffer = self.get_buffer();
+        let lower = self.read_header(&buffer).lower;
+        self.free_slot(&buffer, lower)
+            .unwrap_or_else(|| self.num_slots(lower))
+    }
+
+    // Saturates so a corrupt `lower` below the header reads as an empty page.
+    fn num_slots(&self, lower: u16) -> usize {
+        lower.saturating_sub(self.header_offsets.end_headers) as usize / 2
+    }
+
+    pub fn header(&self) -> PageHeaderInfo {
+        let buffer = self.get_buffer();
+        let header_offsets = &self.header_offsets;
+        let page_header = self.read_header(&buffer);
+        PageHeaderInfo {
+            magic: header_offsets
+                .magic
+                .map(|magic| buffer[magic.0..magic.1].try_into().unwrap()),
+            version: header_offsets
+                .version
+                .map_or(header_offsets.format_version, |version| buffer[version]),
+            flags: header_offsets.flags.map_or(0, |flags| buffer[flags]),
+            id: page_header.id,
+            lower: page_header.lower,
+            higher: page_header.higher,
+            next_page: header_offsets.next_page.map(|next_page| {
+                u16::from_le_bytes(
+                    self.endianness
+                        .order(buffer[next_page.0..next_page.1].try_into().unwrap()),
+                )
+            }),
+            checksum: header_offsets.checksum.map(|checksum| {
+                u32::from_le_bytes(
+                    self.endianness
+                        .order(buffer[checksum.0..checksum.1].try_into().unwrap()),
+                )
+            }),
+            end_headers: header_offsets.end_headers,
+        }
+    }
+
+    /// The slot pointer array in slot order, with `0` for deleted slots.
+    pub fn slot_pointers(&self) -> Vec<u16> {
+        let buffer = self.get_buffer();
+        let page_header = self.read_header(&buffer);
+        (0..self.num_slots(page_header.lower))
+            .map_while(|slot| self.pointer_at(&buffer, slot).ok())
+            .collect()
+    }
+
+    /// Number of entries in the slot pointer array, including tombstones.
+    pub fn slot_count(&self) -> usize {
+        self.num_slots(self.read_metadata().lower)
+    }
+
+    fn pointer_at(&self, buffer: &[u8], slot: usize) -> Result<u16, PageError> {
+        let mut pointer_offset = self.header_offsets.end_headers as usize + slot * 2;
+        Self::read_u16(buffer, &mut pointer_offset, self.endianness)
+    }
+
+    fn read_pointer(&self, buffer: &[u8], slot: usize) -> Result<u16, PageError> {
+        let page_header = self.read_header(buffer);
+        if slot >= self.num_slots(page_header.lower) {
+            return Err(PageError::InvalidSlot(slot));
+        }
+        let pointer = self.pointer_at(buffer, slot)?;
+        if pointer == TOMBSTONE {
+            return Err(PageError::InvalidSlot(slot));
+        }
+        Ok(pointer)
+    }
+
+    fn write_pointer(&self, buffer: &mut [u8], slot: usize, pointer: u16) {
+        let pointer_offset = self.header_offsets.end_headers as usize + slot * 2;
+        buffer[pointer_offset..pointer_offset + 2]
+            .copy_from_slice(&self.endianness.order(pointer.to_le_bytes()));
+    }
+
+    pub fn delete(&self, slot: usize) -> Result<(), PageError> {
+        let mut buffer = self.get_write_buffer();
+        self.read_pointer(&buffer, slot)?;
+        self.write_pointer(&mut buffer, slot, TOMBSTONE);
+        Ok(())
+    }
+
+    pub fn delete_where<F: Fn(&[DataField]) -> bool>(&self, pred: F) -> Result<usize, PageError> {
+        let mut buffer = self.get_write_buffer();
+        let page_header = self.read_header(&buffer);
+        let mut deleted = 0;
+        for slot in 0..self.num_slots(page_header.lower) {
+            let pointer = self.pointer_at(&buffer, slot)?;
+            if pointer == TOMBSTONE
+                || !pred(&Self::decode_row_as(
+                    &buffer,
+                    pointer,
+                    slot,
+                    self.endianness,
+                    self.text_encoding,
+                )?)
+            {
+                continue;
+            }
+            let pointer_offset = self.header_offsets.end_headers as usize + slot * 2;
+            buffer[pointer_offset..pointer_offset + 2]
+                .copy_from_slice(&self.endianness.order(TOMBSTONE.to_le_bytes()));
+            deleted += 1;
+        }
+        Ok(deleted)
+    }
+
+    // Bytes the row at `pointer` takes in the buffer. This can be less than
+    // `row_size` of its fields, for packed integers or Latin-1 text.
+    fn stored_row_len(&self, buffer: &[u8], pointer: u16) -> Result<usize, PageError> {
+        let mut offset = pointer as usize;
+        for datatype_num in Self::read_datatype_nums(buffer, &mut offset, self.endianness)? {
+            Self::skip_field(buffer, &mut offset, datatype_num, self.endianness)?;
+        }
+        Ok(offset - pointer as usize)
+    }
+
+    /// Replaces the row in `slot`, in place if the new row is no longer and
+    /// otherwise at the top of the free space. The whole update holds the
+    /// buffer's write lock, so concurrent writers never claim the same bytes.
+    pub fn update(&self, slot: usize, data_fields: &[DataField]) -> Result<(), PageError> {
+        let mut buffer = self.get_write_buffer();
+        let pointer = self.read_pointer(&buffer, slot)?;
+        let old_row_len = self.stored_row_len(&buffer, pointer)?;
+        let row = Self::encode_row_as(data_fields, self.endianness, self.text_encoding)?;
+        if row.len() <= old_row_len {
+            buffer[pointer as usize..pointer as usize + row.len()].copy_from_slice(&row);
+            self.add_to_filter(&mut buffer, data_fields);
+            return Ok(());
+        }
+        let mut page_header = self.read_header(&buffer);
+        if row.len() > (page_header.higher - page_header.lower) as usize {
+            return Err(PageError::PageFull);
+        }
+        page_header.higher -= row.len() as u16;
+        let data_offset = page_header.higher;
+        buffer[data_offset as usize..data_offset as usize + row.len()].copy_from_slice(&row);
+        self.add_to_filter(&mut buffer, data_fields);
+        self.write_pointer(&mut buffer, slot, data_offset);
+        self.write_header(&mut buffer, &page_header);
+        Ok(())
+    }
+
+    /// Discards every row by resetting the header and emptying any bloom
+    /// filter. The old row bytes stay in the buffer until later writes
+    /// overwrite them.
+    pub fn clear(&self) {
+        let mut buffer = self.get_write_buffer();
+        let mut page_header = self.read_header(&buffer);
+        page_header.lower = self.header_offsets.end_headers;
+        page_header.higher = self.data_end(&buffer) as u16;
+        if let Some(region) = self.filter_region(&buffer) {
+            buffer[region + 2..].fill(0);
+        }
+        self.write_header(&mut buffer, &page_header);
+    }
+
+    /// Replaces every row with `rows`. They are written into a scratch copy
+    /// that is swapped in only once all of them fit, so on `PageFull` the page
+    /// is left exactly as it was.
+    pub fn rewrite(&self, rows: &[Vec<DataField>]) -> Result<(), PageError> {
+        let scratch = self.empty_copy();
+        for row in rows {
+            scratch.write(row)?;
+        }
+        *self.get_write_buffer() = std::mem::take(&mut *scratch.get_write_buffer());
+        Ok(())
+    }
+
+    // A copy of the page with the same header and flags but no rows.
+    fn empty_copy(&self) -> Page {
+        let scratch = self.snapshot().page;
+        scratch.clear();
+        let mut buffer = scratch.get_write_buffer();
+        let data_end = scratch.data_end(&buffer);
+        buffer[self.header_offsets.end_headers as usize..data_end].fill(0);
+        drop(buffer);
+        scratch
+    }
+
+    /// Moves the upper half of the live rows into a new page with id `id`,
+    /// leaving the lower half compacted in this one. Rows keep their order,
+    /// but both pages number their slots from 0. The new page has the same
+    /// flags, so it keeps any bloom filter, and rows from `write_ints` stay
+    /// packed in both. The buffer's write lock is held throughout, so no row
+    /// written concurrently is lost.
+    ///
+    /// `Storage::insert` does not split pages, since that would move rows
+    /// whose `RowId` has already been handed out.
+    pub fn split_off(&self, id: u16) -> Result<Page, PageError> {
+        let lower_half = self.empty_copy();
+        let upper_half = self.empty_copy();
+        upper_half.set_id(id);
+        let mut buffer = self.get_write_buffer();
+        let page_header = self.read_header(&buffer);
+        let mut live = Vec::new();
+        for slot in 0..self.num_slots(page_header.lower) {
+            let pointer = self.pointer_at(&buffer, slot)?;
+            if pointer != TOMBSTONE {
+                live.push((slot, pointer));
+            }
+        }
+        let half = live.len() / 2;
+        for (i, &(slot, pointer)) in live.iter().enumerate() {
+            let target = if i < half { &lower_half } else { &upper_half };
+            self.copy_row(&buffer, slot, pointer, target)?;
+        }
+        *buffer = std::mem::take(&mut *lower_half.get_write_buffer());
+        Ok(upper_half)
+    }
+
+    // Appends the row at `pointer` to `target`, copying rows from
+    // `write_ints` as they are and re-encoding the rest.
+    fn copy_row(
+        &self,
+        buffer: &[u8],
+        slot: usize,
+        pointer: u16,
+        target: &Page,
+    ) -> Result<(), PageError> {
+        let data_fields =
+            Self::decode_row_as(buffer, pointer, slot, self.endianness, self.text_encoding)?;
+        let num_of_fields = Self::read_u16(buffer, &mut (pointer as usize), self.endianness)?;
+        if num_of_fields & PACKED_INTS != 0 {
+            let start = pointer as usize;
+            let row = &buffer[start..start + self.stored_row_len(buffer, pointer)?];
+            target.write_encoded(row, &data_fields)?;
+        } else {
+            target.write(&data_fields)?;
+        }
+        Ok(())
+    }
+
+    /// Moves as many of `other`'s live rows into this page as fit, deleting
+    /// each one from `other`, and returns how many moved. A row that does not
+    /// fit is left behind and later, smaller rows are still tried. If every
+    /// row moved, `other` is cleared.
+    pub fn merge_from(&self, other: &Page) -> Result<usize, PageError> {
+        let mut moved = 0;
+        for (slot, pointer) in other.slot_pointers().into_iter().enumerate() {
+            if pointer == TOMBSTONE {
+                continue;
+            }
+            let row = other.get_row(slot)?;
+            match self.write(&row) {
+                Ok(()) => {}
+                Err(PageError::PageFull) => continue,
+                Err(error) => return Err(error),
+            }
+            other.delete(slot)?;
+            moved += 1;
+        }
+        if other.count() == 0 {
+            other.clear();
+        }
+        Ok(moved)
+    }
+
+    /// Rewrites the live rows contiguously, dropping dead space. Rows from
+    /// `write_ints` are copied as they are rather than re-encoded. The rows
+    /// are written into a scratch copy first, so on an error the page is
+    /// left exactly as it was.
+    pub fn compact(&self) -> Result<(), PageError> {
+        let scratch = self.empty_copy();
+        let mut buffer = self.get_write_buffer();
+        let page_header = self.read_header(&buffer);
+        for slot in 0..self.num_slots(page_header.lower) {
+            let pointer = self.pointer_at(&buffer, slot)?;
+            if pointer != TOMBSTONE {
+                self.copy_row(&buffer, slot, pointer, &scratch)?;
+            }
+        }
+        *buffer = std::mem::take(&mut *scratch.get_write_buffer());
+        Ok(())
+    }
+
+    /// Returns a compacted copy of the page, leaving this one untouched.
+    /// Like `compact`, it keeps rows from `write_ints` packed.
+    pub fn defragment(&self) -> Result<Page, PageError> {
+        let page = self.snapshot().page;
+        page.compact()?;
+        Ok(page)
+    }
+
+    pub fn read(&self) -> Result<Vec<Vec<DataField>>, PageError> {
+        self.iter().collect()
+    }
+
+    pub fn get_row(&self, slot: usize) -> Result<Vec<DataField>, PageError> {
+        let buffer = self.get_buffer();
+        let pointer = self.read_pointer(&buffer, slot)?;
+        Self::decode_row_as(&buffer, pointer, slot, self.endianness, self.text_encoding)
+    }
+
+    /// Decodes field `field_index` of the row in `slot`, skipping over the
+    /// payloads before it and leaving the rest of the row untouched.
+    pub fn field_at(&self, slot: usize, field_index: usize) -> Result<DataField, PageError> {
+        let buffer = self.get_buffer();
+        let pointer = self.read_pointer(&buffer, slot)?;
+        let mut offset = pointer as usize;
+        let datatype_nums = Self::read_datatype_nums(&buffer, &mut offset, self.endianness)?;
+        let &datatype_num = datatype_nums
+            .get(field_index)
+            .ok_or(PageError::FieldOutOfRange(field_index))?;
+        for &skipped in &datatype_nums[..field_index] {
+            Self::skip_field(&buffer, &mut offset, skipped, self.endianness)?;
+        }
+        Self::decode_field(
+            &buffer,
+            &mut offset,
+            datatype_num,
+            slot,
+            self.endianness,
+            self.text_encoding,
+        )
+    }
+
+    pub fn count(&self) -> usize {
+        let page_header = self.read_metadata();
+        let buffer = self.get_buffer();
+        (0..self.num_slots(page_header.lower))
+            .filter(|&slot| matches!(self.pointer_at(&buffer, slot), Ok(pointer) if pointer != TOMBSTONE))
+            .count()
+    }
+
+    /// Checks the page's structure: `end_headers <= lower <= higher <=
+    /// page_size`, every live slot points into the data region, and every row
+    /// decodes without running past the end of the page. Violations are
+    /// reported as `CorruptPage` at the offending header field, slot pointer
+    /// or row offset.
+    pub fn validate(&self) -> Result<(), PageError> {
+        let buffer = self.get_buffer();
+        let header_offsets = &self.header_offsets;
+        let page_header = self.read_header(&buffer);
+        let end_headers = header_offsets.end_headers as usize;
+        let (lower, higher) = (page_header.lower as usize, page_header.higher as usize);
+        if lower < end_headers || !(lower - end_headers).is_multiple_of(2) || lower > higher {
+            return Err(PageError::CorruptPage {
+                offset: header_offsets.lower.0,
+            });
+        }
+        if higher > self.page_size || higher > buffer.len() {
+            return Err(PageError::CorruptPage {
+                offset: header_offsets.higher.0,
+            });
+        }
+        for slot in 0..self.num_slots(page_header.lower) {
+            let pointer = self.pointer_at(&buffer, slot)?;
+            if pointer == TOMBSTONE {
+                continue;
+            }
+            if (pointer as usize) < higher {
+                return Err(PageError::CorruptPage {
+                    offset: end_headers + slot * 2,
+                });
+            }
+            Self::decode_row_as(&buffer, pointer, slot, self.endianness, self.text_encoding)?;
+        }
+        Ok(())
+    }
+
+    /// Breaks the page down into used, free and dead bytes, which always sum
+    /// to the page size.
+    pub fn stats(&self) -> Result<PageStats, PageError> {
+        let buffer = self.get_buffer();
+        let page_header = self.read_header(&buffer);
+        let mut num_rows = 0;
+        let mut live_bytes = 0;
+        for slot in 0..self.num_slots(page_header.lower) {
+            let pointer = self.pointer_at(&buffer, slot)?;
+            if pointer != TOMBSTONE {
+                num_rows += 1;
+                live_bytes += self.stored_row_len(&buffer, pointer)?;
+            }
+        }
+        let data_end = self.data_end(&buffer);
+        let data_bytes = data_end - page_header.higher as usize;
+        Ok(PageStats {
+            num_rows,
+            used_bytes: page_header.lower as usize + live_bytes + self.page_size - data_end,
+            free_bytes: (page_header.higher - page_header.lower) as usize,
+            dead_bytes: data_bytes.saturating_sub(live_bytes),
+        })
+    }
+
+    /// Decodes the rows in slots `start..end`, skipping deleted slots. `end`
+    /// is clamped to the number of slots; `start > end` is an `InvalidSlot`
+    /// error.
+    pub fn read_range(&self, start: usize, end: usize) -> Result<Vec<Vec<DataField>>, PageError> {
+        if start > end {
+            return Err(PageError::InvalidSlot(start));
+        }
+        let page_header = self.read_metadata();
+        let buffer = self.get_buffer();
+        let end = end.min(self.num_slots(page_header.lower));
+        let mut rows = Vec::new();
+        for slot in start..end {
+            let pointer = self.pointer_at(&buffer, slot)?;
+            if pointer != TOMBSTONE {
+                rows.push(Self::decode_row_as(
+                    &buffer,
+                    pointer,
+                    slot,
+                    self.endianness,
+                    self.text_encoding,
+                )?);
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Decodes only the fields at `indices`, returned in that order, skipping
+    /// over the payloads of every other field.
+    pub fn read_projected(&self, indices: &[usize]) -> Result<Vec<Vec<DataField>>, PageError> {
+        let page_header = self.read_metadata();
+        let buffer = self.get_buffer();
+        let mut rows = Vec::new();
+        for slot in 0..self.num_slots(page_header.lower) {
+            let pointer = self.pointer_at(&buffer, slot)?;
+            if pointer != TOMBSTONE {
+                rows.push(Self::decode_projected_row(
+                    &buffer,
+                    pointer,
+                    slot,
+                    indices,
+                    self.endianness,
+                    self.text_encoding,
+                )?);
+            }
+        }
+        Ok(rows)
+    }
+
+    /// The distinct values of field `field_index` in first-seen order,
+    /// decoding only that field of each row.
+    pub fn distinct(&self, field_index: usize) -> Result<Vec<DataField>, PageError> {
+        let mut values = Vec::new();
+        for row in self.read_projected(&[field_index])? {
+            push_distinct(&mut values, row.into_iter().next().unwrap());
+        }
+        Ok(values)
+    }
+
+    /// Whether any row matches `pred`. Rows are decoded in slot order and
+    /// decoding stops at the first match.
+    pub fn any<F: Fn(&[DataField]) -> bool>(&self, pred: F) -> Result<bool, PageError> {
+        for row in self.iter() {
+            if pred(&row?) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Lazily decodes rows in slot order. The iterator holds the page's read
+    /// lock until it is dropped, so writes to the page block in the meantime.
+    pub fn iter(&self) -> PageRowIter<'_> {
+        let buffer = self.get_buffer();
+        let lower_offsets = self.header_offsets.lower;
+        let lower = u16::from_le_bytes(
+            self.endianness
+                .order(buffer[lower_offsets.0..lower_offsets.1].try_into().unwrap()),
+        );
+        PageRowIter {
+            buffer,
+            end_headers: self.header_offsets.end_headers as usize,
+            num_slots: self.num_slots(lower),
+            slot: 0,
+            endianness: self.endianness,
+            text_encoding: self.text_encoding,
+        }
+    }
+}
+
+/// Read-only copy of a page taken by `Page::snapshot`. Later writes to the
+/// page are not visible through it.
+pub struct PageSnapshot {
+    page: Page,
+}
+
+impl PageSnapshot {
+    pub fn id(&self) -> u16 {
+        self.page.id()
+    }
+
+    pub fn read(&self) -> Result<Vec<Vec<DataField>>, PageError> {
+        self.page.read()
+    }
+
+    pub fn get_row(&self, slot: usize) -> Result<Vec<DataField>, PageError> {
+        self.page.get_row(slot)
+    }
+
+    pub fn read_projected(&self, indices: &[usize]) -> Result<Vec<Vec<DataField>>, PageError> {
+        self.page.read_projected(indices)
+    }
+
+    pub fn count(&self) -> usize {
+        self.page.count()
+    }
+
+    pub fn iter(&self) -> PageRowIter<'_> {
+        self.page.iter()
+    }
+}
+
+/// Read-only view of a page over a borrowed buffer, such as one page of a
+/// file read into memory whole. Rows are decoded straight from the slice
+/// without copying it into a page buffer first.
+pub struct PageView<'a> {
+    buffer: &'a [u8],
+    header_offsets: PageHeaderOffsets,
+    page_size: usize,
+    endianness: Endianness,
+    text_encoding: TextEncoding,
+}
+
+impl<'a> PageView<'a> {
+    /// Views `buffer` as a whole page, failing as `Page::check_format` does
+    /// and, when `verify` is set, as `Page::verify` does.
+    pub fn new(buffer: &'a [u8], verify: bool) -> Result<Self, PageError> {
+        let (header_offsets, endianness, text_encoding) = Page::layout(buffer);
+        if buffer.len() < header_offsets.end_headers as usize {
+            return Err(PageError::CorruptPage {
+                offset: buffer.len(),
+            });
+        }
+        let view = PageView {
+            buffer,
+            header_offsets,
+            page_size: buffer.len(),
+            endianness,
+            text_encoding,
+        };
+        view.check_format()?;
+        if verify {
+            view.verify()?;
+        }
+        Ok(view)
+    }
+
+    // Reads `lower` and `higher` from the header.
+    fn bounds(&self) -> Result<(usize, usize), PageError> {
+        let lower = Page::read_u16(
+            self.buffer,
+            &mut { self.header_offsets.lower.0 },
+            self.endianness,
+        )?;
+        let higher = Page::read_u16(
+            self.buffer,
+            &mut { self.header_offsets.higher.0 },
+            self.endianness,
+        )?;
+        Ok((lower as usize, higher as usize))
+    }
+
+    fn check_format(&self) -> Result<(), PageError> {
+        let buffer = self.buffer;
+        let header_offsets = &self.header_offsets;
+        let Some(magic) = header_offsets.magic else {
+            // Version 1 pages have no magic bytes, so anything else without
+            // them is only accepted if its header is plausible.
+            let (lower, higher) = self.bounds()?;
+            if lower < header_offsets.end_headers as usize
+                || lower > higher
+                || higher > self.page_size
+            {
+                return Err(PageError::BadMagic);
+            }
+            return Ok(());
+        };
+        if &buffer[magic.0..magic.1] != MAGIC {
+            return Err(PageError::BadMagic);
+        }
+        let version = buffer[header_offsets.version.unwrap()];
+        if version != header_offsets.format_version {
+            return Err(PageError::UnsupportedVersion(version));
+        }
+        if let Some(flags) = header_offsets.flags {
+            let flags = buffer[flags];
+            if flags & !KNOWN_FLAGS != 0 {
+                return Err(PageError::UnknownFlags(flags));
+            }
+        }
+        Ok(())
+    }
+
+    fn verify(&self) -> Result<(), PageError> {
+        let Some(checksum_offsets) = self.header_offsets.checksum else {
+            return Ok(());
+        };
+        let stored = u32::from_le_bytes(
+            self.endianness.order(
+                self.buffer[checksum_offsets.0..checksum_offsets.1]
+                    .try_into()
+                    .unwrap(),
+            ),
+        );
+        if stored != Page::compute_checksum(self.buffer, checksum_offsets) {
+            return Err(PageError::ChecksumMismatch);
+        }
+        Ok(())
+    }
+
+    pub fn is_overflow(&self) -> bool {
+        self.header_offsets
+            .flags
+            .is_some_and(|flags| self.buffer[flags] & FLAG_OVERFLOW != 0)
+    }
+
+    /// Decodes every live row, like `Page::read`.
+    pub fn read(&self) -> Result<Vec<Vec<DataField>>, PageError> {
+        let end_headers = self.header_offsets.end_headers as usize;
+        let (lower, _) = self.bounds()?;
+        let num_slots = lower.saturating_sub(end_headers) / 2;
+        let mut rows = Vec::new();
+        for slot in 0..num_slots {
+            let pointer =
+                Page::read_u16(self.buffer, &mut (end_headers + slot * 2), self.endianness)?;
+            if pointer != TOMBSTONE {
+                rows.push(Page::decode_row_as(
+                    self.buffer,
+                    pointer,
+                    slot,
+                    self.endianness,
+                    self.text_encoding,
+                )?);
+            }
+        }
+        Ok(rows)
+    }
+}
+
+pub struct PageRowIter<'a> {
+    buffer: RwLockReadGuard<'a, Vec<u8>>,
+    end_headers: usize,
+    num_slots: usize,
+    slot: usize,
+    endianness: Endianness,
+    text_encoding: TextEncoding,
+}
+
+impl Iterator for PageRowIter<'_> {
+    type Item = Result<Vec<DataField>, PageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.slot < self.num_slots {
+            let slot = self.slot;
+            let mut pointer_offset = self.end_headers + slot * 2;
+            self.slot += 1;
+            let pointer = match Page::read_u16(&self.buffer, &mut pointer_offset, self.endianness) {
+                Ok(pointer) => pointer,
+                Err(err) => return Some(Err(err)),
+            };
+            if pointer != TOMBSTONE {
+                return Some(Page::decode_row_as(
+                    &self.buffer,
+                    pointer,
+                    slot,
+                    self.endianness,
+                    self.text_encoding,
+                ));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read_page() {
+        let page = Page::new(0, 8192, None);
+        let data_fields = vec![
+            DataField::Text("data".to_string()),
+            DataField::Integer(10),
+            DataField::Int32(-12345),
+        ];
+        page.write(&data_fields).unwrap();
+        let rows = page.read().unwrap();
+        assert_eq!(rows, vec![data_fields]);
+    }
+
+    #[test]
+    fn test_write_and_read_int64() {
+        let page = Page::new(0, 8192, None);
+        let data_fields = vec![DataField::Int64(1_700_000_000_000)];
+        page.write(&data_fields).unwrap();
+        let rows = page.read().unwrap();
+        assert_eq!(rows, vec![data_fields]);
+    }
+
+    #[test]
+    fn test_write_and_read_float64() {
+        let page = Page::new(0, 8192, None);
+        page.write(&[DataField::Float64(1234.5678), DataField::Float64(f64::NAN)])
+            .unwrap();
+        let rows = page.read().unwrap();
+        let bits: Vec<u64> = rows[0]
+            .iter()
+            .map(|field| match field {
+                DataField::Float64(float) => float.to_bits(),
+                _ => panic!("expected a float"),
+            })
+            .collect();
+        assert_eq!(bits, vec![1234.5678f64.to_bits(), f64::NAN.to_bits()]);
+    }
+
+    #[test]
+    fn test_write_and_read_null() {
+        let page = Page::new(0, 8192, None);
+        let data_fields = vec![
+            DataField::Text("x".into()),
+            DataField::Null,
+            DataField::Integer(5),
+        ];
+        page.write(&data_fields).unwrap();
+        let rows = page.read().unwrap();
+        assert_eq!(rows, vec![data_fields]);
+    }
+
+    #[test]
+    fn test_write_and_read_bool() {
+        let page = Page::new(0, 8192, None);
+        let data_fields = vec![
+            DataField::Bool(true),
+            DataField::Integer(7),
+            DataField::Bool(false),
+        ];
+        page.write(&data_fields).unwrap();
+        page.write(&[DataField::Bool(false)]).unwrap();
+        let rows = page.read().unwrap();
+        assert_eq!(rows, vec![data_fields, vec![DataField::Bool(false)]]);
+    }
+
+    #[test]
+    fn test_write_and_read_bytes() {
+        let page = Page::new(0, 8192, None);
+        let data_fields = vec![DataField::Bytes(vec![0xFF, 0xFE, 0x00])];
+        page.write(&data_fields).unwrap();
+        let rows = page.read().unwrap();
+        assert_eq!(rows, vec![data_fields]);
+    }
+
+    #[test]
+    fn test_write_returns_page_full() {
+        let page = Page::new(0, 64, None);
+        let data_fields = vec![DataField::Integer(1), DataField::Integer(2)];
+        let mut written = 0;
+        while page.write(&data_fields).is_ok() {
+            written += 1;
+        }
+        assert_eq!(page.write(&data_fields), Err(PageError::PageFull));
+        assert!(written > 0);
+        assert_eq!(page.read().unwrap(), vec![data_fields; written]);
+    }
+
+    #[test]
+    fn test_free_space_decreases_after_write() {
+        let page = Page::new(0, 8192, None);
+        assert_eq!(page.free_space(), 8192 - 18);
+        // 2 byte field count + 2 byte type tag + 2 byte value + 2 byte slot pointer
+        page.write(&[DataField::Integer(1)]).unwrap();
+        assert_eq!(page.free_space(), 8192 - 18 - 8);
+        // 2 byte field count + 2 byte type tag + 2 byte length + 4 bytes + 2 byte slot pointer
+        page.write(&[DataField::Text("text".to_string())]).unwrap();
+        assert_eq!(page.free_space(), 8192 - 18 - 8 - 12);
+    }
+
+    #[test]
+    fn test_row_size_matches_free_space_delta() {
+        let page = Page::new(0, 8192, None);
+        let data_fields = vec![
+            DataField::Integer(1),
+            DataField::Text("text".to_string()),
+            DataField::Null,
+            DataField::Bytes(vec![1, 2, 3]),
+            DataField::LargeText("large".to_string()),
+            DataField::Timestamp(5),
+        ];
+        let before = page.free_space();
+        page.write(&data_fields).unwrap();
+        assert_eq!(row_size(&data_fields) + 2, before - page.free_space());
+    }
+
+    #[test]
+    fn test_can_fit() {
+        let page = Page::new(0, 64, None);
+        let data_fields = vec![DataField::Integer(1), DataField::Text("ab".to_string())];
+        assert!(page.can_fit(&data_fields));
+        while page.can_fit(&data_fields) {
+            page.write(&data_fields).unwrap();
+        }
+        // 2 byte field count + 2 type tags + 2 byte integer + 4 byte text + 2 byte slot pointer
+        assert!(page.free_space() < 14);
+        assert_eq!(page.write(&data_fields), Err(PageError::PageFull));
+    }
+
+    #[test]
+    fn test_read_invalid_utf8() {
+        let page = Page::new(0, 64, None);
+        page.write(&[DataField::Text("ok".to_string())]).unwrap();
+        page.write(&[DataField::Text("ab".to_string())]).unwrap();
+        let mut buffer = page.get_buffer().clone();
+        let text_offset = buffer.windows(2).position(|bytes| bytes == b"ab").unwrap();
+        buffer[text_offset] = 0xFF;
+        let corrupt_page = Page::new(0, 64, Some(buffer));
+        assert_eq!(corrupt_page.read(), Err(PageError::InvalidUtf8 { slot: 1 }));
+    }
+
+    #[test]
+    fn test_read_unknown_type() {
+        let page = Page::new(0, 64, None);
+        page.write(&[DataField::Integer(5)]).unwrap();
+        let mut buffer = page.get_buffer().clone();
+        let pointer_offset = page.header_offsets.end_headers as usize;
+        let data_offset = u16::from_le_bytes(
+            buffer[pointer_offset..pointer_offset + 2]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        buffer[data_offset + 2..data_offset + 4].copy_from_slice(&99u16.to_le_bytes());
+        let corrupt_page = Page::new(0, 64, Some(buffer));
+        assert_eq!(corrupt_page.read(), Err(PageError::UnknownType(99)));
+    }
+
+    #[test]
+    fn test_verify_detects_corruption() {
+        let page = Page::new(0, 8192, None);
+        page.write(&[DataField::Text("checked".to_string())])
+            .unwrap();
+        page.seal();
+        assert_eq!(page.verify(), Ok(()));
+        let mut buffer = page.get_buffer().clone();
+        buffer[8191] ^= 0x01;
+        let corrupt_page = Page::new(0, 8192, Some(buffer));
+        assert_eq!(corrupt_page.verify(), Err(PageError::ChecksumMismatch));
+    }
+
+    #[test]
+    fn test_delete_row() {
+        let page = Page::new(0, 8192, None);
+        let rows = vec![
+            vec![DataField::Integer(1)],
+            vec![DataField::Integer(2)],
+            vec![DataField::Integer(3)],
+        ];
+        for row in &rows {
+            page.write(row).unwrap();
+        }
+        page.delete(1).unwrap();
+        assert_eq!(page.read().unwrap(), vec![rows[0].clone(), rows[2].clone()]);
+        assert_eq!(page.delete(1), Err(PageError::InvalidSlot(1)));
+        assert_eq!(page.delete(3), Err(PageError::InvalidSlot(3)));
+    }
+
+    #[test]
+    fn test_update_row() {
+        let page = Page::new(0, 8192, None);
+        page.write(&[DataField::Integer(1)]).unwrap();
+        page.write(&[DataField::Integer(2)]).unwrap();
+        let free_space = page.free_space();
+        let longer = vec![DataField::Text("much longer string".to_string())];
+        page.update(0, &longer).unwrap();
+        assert!(page.free_space() < free_space);
+        assert_eq!(
+            page.read().unwrap(),
+            vec![longer.clone(), vec![DataField::Integer(2)]]
+        );
+        let free_space = page.free_space();
+        page.update(0, &[DataField::Integer(3)]).unwrap();
+        assert_eq!(page.free_space(), free_space);
+        assert_eq!(
+            page.read().unwrap(),
+            vec![vec![DataField::Integer(3)], vec![DataField::Integer(2)]]
+        );
+        assert_eq!(page.update(2, &longer), Err(PageError::InvalidSlot(2)));
+    }
+
+    #[test]
+    fn test_compact_reclaims_space() {
+        let page = Page::new(0, 8192, None);
+        let rows: Vec<Vec<DataField>> = (0..6)
+            .map(|i| vec![DataField::Integer(i), DataField::Text(format!("row {}", i))])
+            .collect();
+        for row in &rows {
+            page.write(row).unwrap();
+        }
+        page.delete(1).unwrap();
+        page.delete(4).unwrap();
+        let free_space = page.free_space();
+        page.compact().unwrap();
+        assert!(page.free_space() > free_space);
+        assert_eq!(
+            page.read().unwrap(),
+            vec![
+                rows[0].clone(),
+                rows[2].clone(),
+                rows[3].clone(),
+                rows[5].clone()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_iter_matches_read() {
+        let page = Page::new(0, 8192, None);
+        for i in 0..5 {
+            page.write(&[DataField::Integer(i)]).unwrap();
+        }
+        page.delete(2).unwrap();
+        let rows: Vec<Vec<DataField>> = page.iter().map(|row| row.unwrap()).collect();
+        assert_eq!(rows, page.read().unwrap());
+    }
+
+    #[test]
+    fn test_iter_is_lazy() {
+        let page = Page::new(0, 64, None);
+        page.write(&[DataField::Integer(1)]).unwrap();
+        page.write(&[DataField::Integer(2)]).unwrap();
+        let mut buffer = page.get_buffer().clone();
+        let pointer_offset = page.header_offsets.end_headers as usize + 2;
+        let data_offset = u16::from_le_bytes(
+            buffer[pointer_offset..pointer_offset + 2]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        buffer[data_offset + 2..data_offset + 4].copy_from_slice(&99u16.to_le_bytes());
+        let corrupt_page = Page::new(0, 64, Some(buffer));
+        let rows: Vec<_> = corrupt_page.iter().take(1).collect();
+        assert_eq!(rows, vec![Ok(vec![DataField::Integer(1)])]);
+        assert_eq!(corrupt_page.read(), Err(PageError::UnknownType(99)));
+    }
+
+    #[test]
+    fn test_write_and_read_large_text() {
+        let page = Page::new(0, u16::MAX as usize, None);
+        let data_fields = vec![DataField::LargeText("a".repeat(60_000))];
+        page.write(&data_fields).unwrap();
+        assert_eq!(page.read().unwrap(), vec![data_fields]);
+        let page = Page::new(0, u16::MAX as usize, None);
+        assert_eq!(
+            page.write(&[DataField::LargeText("a".repeat(70_000))]),
+            Err(PageError::PageFull)
+        );
+    }
+
+    #[test]
+    fn test_write_rejects_text_too_long() {
+        let page = Page::new(0, u16::MAX as usize, None);
+        assert_eq!(
+            page.write(&[DataField::Text("a".repeat(70_000))]),
+            Err(PageError::TextTooLong { len: 70_000 })
+        );
+        // Batches report the typed error too rather than treating the row as
+        // not fitting.
+        assert_eq!(
+            page.write_batch(&[
+                vec![DataField::Integer(1)],
+                vec![DataField::Text("a".repeat(70_000))],
+            ]),
+            Err(PageError::TextTooLong { len: 70_000 })
+        );
+        assert_eq!(page.read().unwrap(), vec![vec![DataField::Integer(1)]]);
+    }
+
+    #[test]
+    fn test_read_empty_page() {
+        let page = Page::new(0, 8192, None);
+        assert_eq!(page.read().unwrap(), Vec::<Vec<DataField>>::new());
+        let mut buffer = page.get_buffer().clone();
+        buffer[page.header_offsets.lower.0..page.header_offsets.lower.1]
+            .copy_from_slice(&0u16.to_le_bytes());
+        let corrupt_page = Page::new(0, 8192, Some(buffer));
+        assert_eq!(corrupt_page.read().unwrap(), Vec::<Vec<DataField>>::new());
+    }
+
+    #[test]
+    fn test_read_projected() {
+        let page = Page::new(0, 8192, None);
+        for i in 0..4 {
+            page.write(&[
+                DataField::Integer(i),
+                DataField::Text(format!("row {}", i)),
+                DataField::Int64(i as i64 * 10),
+            ])
+            .unwrap();
+        }
+        assert_eq!(
+            page.read_projected(&[0]).unwrap(),
+            (0..4)
+                .map(|i| vec![DataField::Integer(i)])
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            page.read_projected(&[2, 1]).unwrap()[3],
+            vec![DataField::Int64(30), DataField::Text("row 3".to_string())]
+        );
+        assert_eq!(
+            page.read_projected(&[3]),
+            Err(PageError::FieldOutOfRange(3))
+        );
+    }
+
+    #[test]
+    fn test_count() {
+        let page = Page::new(0, 8192, None);
+        assert_eq!(page.count(), 0);
+        for i in 0..7 {
+            page.write(&[DataField::Integer(i)]).unwrap();
+        }
+        assert_eq!(page.count(), 7);
+        page.delete(3).unwrap();
+        assert_eq!(page.count(), 6);
+    }
+
+    #[test]
+    fn test_write_batch() {
+        let rows: Vec<Vec<DataField>> = (0..100)
+            .map(|i| vec![DataField::Integer(i), DataField::Text(format!("row {}", i))])
+            .collect();
+        let single = Page::new(0, 512, None);
+        let mut expected = 0;
+        while single.write(&rows[expected]).is_ok() {
+            expected += 1;
+        }
+        let batched = Page::new(0, 512, None);
+        let written = batched.write_batch(&rows).unwrap();
+        assert_eq!(written, expected);
+        assert!(written < rows.len());
+        assert_eq!(*batched.get_buffer(), *single.get_buffer());
+        assert_eq!(batched.read().unwrap(), rows[..written].to_vec());
+    }
+
+    #[test]
+    fn test_check_format() {
+        let page = Page::new(0, 64, None);
+        assert_eq!(page.check_format(), Ok(()));
+        let mut buffer = page.get_buffer().clone();
+        buffer[page.header_offsets.version.unwrap()] = FORMAT_VERSION + 1;
+        let future_page = Page::new(0, 64, Some(buffer));
+        assert_eq!(
+            future_page.check_format(),
+            Err(PageError::UnsupportedVersion(FORMAT_VERSION + 1))
+        );
+        let foreign_page = Page::new(0, 64, Some(vec![0xAB; 64]));
+        assert_eq!(foreign_page.check_format(), Err(PageError::BadMagic));
+    }
+
+    #[test]
+    fn test_get_row() {
+        let page = Page::new(0, 8192, None);
+        for i in 0..3 {
+            page.write(&[DataField::Integer(i), DataField::Text(format!("row {}", i))])
+                .unwrap();
+        }
+        assert_eq!(page.get_row(1).unwrap(), page.read().unwrap()[1]);
+        assert_eq!(page.get_row(3), Err(PageError::InvalidSlot(3)));
+        page.delete(1).unwrap();
+        assert_eq!(page.get_row(1), Err(PageError::InvalidSlot(1)));
+    }
+
+    #[test]
+    fn test_write_and_read_uint32() {
+        let page = Page::new(0, 8192, None);
+        let data_fields = vec![DataField::UInt32(u32::MAX), DataField::Int32(-1)];
+        page.write(&data_fields).unwrap();
+        assert_eq!(page.read().unwrap(), vec![data_fields]);
+    }
+
+    #[test]
+    fn test_delete_where() {
+        let page = Page::new(0, 8192, None);
+        for i in 0..6 {
+            page.write(&[DataField::Integer(i)]).unwrap();
+        }
+        let deleted = page
+            .delete_where(|row| matches!(row[0], DataField::Integer(i) if i % 2 == 0))
+            .unwrap();
+        assert_eq!(deleted, 3);
+        assert_eq!(
+            page.read().unwrap(),
+            vec![
+                vec![DataField::Integer(1)],
+                vec![DataField::Integer(3)],
+                vec![DataField::Integer(5)]
+            ]
+        );
+    }
 
     #[test]
-    fn test_write_and_read_page() {
-        let page = Page::new(8192, None);
-        let data_fields = vec![DataField::Text("data".to_string()), DataField::Integer(10)];
-        page.write(&data_fields);
-        let rows = page.read();
+    fn test_write_and_read_timestamp() {
+        let page = Page::new(0, 8192, None);
+        // 2023-11-14T22:13:20Z and 1969-12-31T23:59:59.5Z
+        let data_fields = vec![
+            DataField::Timestamp(1_700_000_000_000_000),
+            DataField::Timestamp(-500_000),
+        ];
+        page.write(&data_fields).unwrap();
+        assert_eq!(page.read().unwrap(), vec![data_fields]);
+    }
+
+    #[test]
+    fn test_write_and_read_uuid() {
+        let page = Page::new(0, 8192, None);
+        // 67e55044-10b1-426f-9247-bb680e5fe0c8
+        let uuid = [
+            0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47, 0xbb, 0x68, 0x0e, 0x5f,
+            0xe0, 0xc8,
+        ];
+        let data_fields = vec![DataField::Uuid(uuid)];
+        assert_eq!(row_size(&data_fields) - row_size(&[]), 2 + 16);
+        page.write(&data_fields).unwrap();
+        assert_eq!(page.read().unwrap(), vec![data_fields]);
+    }
+
+    #[test]
+    fn test_read_pointer_past_end_is_corrupt() {
+        let page = Page::new(0, 64, None);
+        page.write(&[DataField::Integer(5)]).unwrap();
+        let mut buffer = page.get_buffer().clone();
+        let pointer_offset = page.header_offsets.end_headers as usize;
+        buffer[pointer_offset..pointer_offset + 2].copy_from_slice(&63u16.to_le_bytes());
+        let corrupt_page = Page::new(0, 64, Some(buffer.clone()));
+        assert_eq!(
+            corrupt_page.read(),
+            Err(PageError::CorruptPage { offset: 63 })
+        );
+
+        // A text length that runs past the end of the page.
+        let page = Page::new(0, 64, None);
+        page.write(&[DataField::Text("abc".to_string())]).unwrap();
+        let mut buffer = page.get_buffer().clone();
+        buffer[64 - 5..64 - 3].copy_from_slice(&1000u16.to_le_bytes());
+        let corrupt_page = Page::new(0, 64, Some(buffer));
+        assert_eq!(
+            corrupt_page.read_projected(&[0]),
+            Err(PageError::CorruptPage { offset: 64 - 3 })
+        );
+    }
+
+    #[test]
+    fn test_write_and_read_varint() {
+        let page = Page::new(0, 8192, None);
+        for (value, len) in [
+            (0, 1),
+            (127, 1),
+            (128, 2),
+            (16383, 2),
+            (16384, 3),
+            (u64::MAX, 10),
+        ] {
+            assert_eq!(
+                row_size(&[DataField::VarInt(value)]) - row_size(&[]),
+                2 + len
+            );
+            page.write(&[DataField::VarInt(value)]).unwrap();
+        }
+        let values: Vec<_> = page.read().unwrap().into_iter().flatten().collect();
+        assert_eq!(
+            values,
+            [0, 127, 128, 16383, 16384, u64::MAX]
+                .map(DataField::VarInt)
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn test_clear() {
+        let page = Page::new(0, 64, None);
+        page.write(&[DataField::Integer(1)]).unwrap();
+        page.write(&[DataField::Text("abc".to_string())]).unwrap();
+        page.clear();
+        assert_eq!(page.read().unwrap(), Vec::<Vec<DataField>>::new());
+        assert_eq!(page.free_space(), 64 - 18);
+        page.write(&[DataField::Integer(2)]).unwrap();
+        assert_eq!(page.read().unwrap(), vec![vec![DataField::Integer(2)]]);
+    }
+
+    #[test]
+    fn test_big_endian_round_trip() {
+        let page = Page::with_endianness(3, 8192, Endianness::Big);
+        let data_fields = vec![
+            DataField::Integer(0x0102),
+            DataField::Int64(-2),
+            DataField::Float64(1234.5678),
+            DataField::Text("text".to_string()),
+            DataField::LargeText("large".to_string()),
+        ];
+        page.write(&data_fields).unwrap();
+        page.seal();
+        let buffer = page.get_buffer().clone();
+        // The header id is stored most significant byte first.
+        assert_eq!(buffer[6..8], [0, 3]);
+        let reopened = Page::new(3, 8192, Some(buffer));
+        assert_eq!(reopened.endianness(), Endianness::Big);
+        reopened.check_format().unwrap();
+        reopened.verify().unwrap();
+        assert_eq!(reopened.read().unwrap(), vec![data_fields]);
+        assert_eq!(reopened.free_space(), page.free_space());
+    }
+
+    #[test]
+    fn test_check_format_rejects_unknown_flags() {
+        let page = Page::new(0, 64, None);
+        let mut buffer = page.get_buffer().clone();
+        buffer[page.header_offsets.flags.unwrap()] = 0x21;
+        let page = Page::new(0, 64, Some(buffer));
+        assert_eq!(page.check_format(), Err(PageError::UnknownFlags(0x21)));
+    }
+
+    #[test]
+    fn test_with_buffer_sees_page_bytes() {
+        let page = Page::new(0, 64, None);
+        page.write(&[DataField::Integer(9)]).unwrap();
+        let copy = page.get_buffer().clone();
+        assert!(page.with_buffer(|buffer| buffer == copy.as_slice()));
+        assert_eq!(page.with_buffer(|buffer| buffer.len()), 64);
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let page = Page::new(2, 8192, None);
+        for i in 0..100 {
+            page.write(&[
+                DataField::Integer(i),
+                DataField::Text("status: pending review by the storage team".to_string()),
+            ])
+            .unwrap();
+        }
+        let data_len = 8192 - page.read_metadata().higher as usize;
+        let bytes = page.to_compressed();
+        assert!(bytes.len() < data_len / 4);
+        assert!(Page::is_compressed(&bytes));
+        assert!(!Page::is_compressed(&page.to_bytes()));
+        let restored = Page::from_compressed(2, 8192, &bytes).unwrap();
+        assert_eq!(restored.read().unwrap(), page.read().unwrap());
+        assert_eq!(*restored.get_buffer(), *page.get_buffer());
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(matches!(
+            Page::from_compressed(2, 8192, truncated),
+            Err(PageError::CorruptPage { .. })
+        ));
+    }
+
+    #[test]
+    fn test_display_data_field() {
+        assert_eq!(DataField::Integer(7).to_string(), "7");
+        assert_eq!(DataField::Null.to_string(), "");
+        assert_eq!(DataField::Bytes(vec![0x0a, 0xff]).to_string(), "0aff");
+        let uuid = [
+            0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47, 0xbb, 0x68, 0x0e, 0x5f,
+            0xe0, 0xc8,
+        ];
+        assert_eq!(
+            DataField::Uuid(uuid).to_string(),
+            "67e55044-10b1-426f-9247-bb680e5fe0c8"
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let page = Page::new(0, 256, None);
+        for i in 0..4 {
+            page.write(&[DataField::Integer(i)]).unwrap();
+        }
+        page.delete(1).unwrap();
+        page.update(2, &[DataField::Int64(2)]).unwrap();
+        let stats = page.stats().unwrap();
+        assert_eq!(stats.num_rows, 3);
+        assert_eq!(stats.used_bytes, 18 + 4 * 2 + 6 + 12 + 6);
+        // The deleted row and the old copy of the updated one.
+        assert_eq!(stats.dead_bytes, 6 + 6);
+        assert_eq!(stats.used_bytes + stats.free_bytes + stats.dead_bytes, 256);
+        page.compact().unwrap();
+        assert_eq!(page.stats().unwrap().dead_bytes, 0);
+    }
+
+    #[test]
+    fn test_validate() {
+        let page = Page::new(0, 64, None);
+        page.write(&[DataField::Integer(1)]).unwrap();
+        page.write(&[DataField::Text("abc".to_string())]).unwrap();
+        page.delete(0).unwrap();
+        assert_eq!(page.validate(), Ok(()));
+        let valid = page.get_buffer().clone();
+        let header_offsets = page.header_offsets.clone();
+        let corrupt = |edit: &dyn Fn(&mut Vec<u8>)| {
+            let mut buffer = valid.clone();
+            edit(&mut buffer);
+            Page::new(0, 64, Some(buffer)).validate()
+        };
+        let set_u16 = |buffer: &mut Vec<u8>, offset: usize, value: u16| {
+            buffer[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+        };
+        let lower_error = Err(PageError::CorruptPage {
+            offset: header_offsets.lower.0,
+        });
+        // lower inside the header
+        assert_eq!(
+            corrupt(&|buffer| set_u16(buffer, header_offsets.lower.0, 10)),
+            lower_error
+        );
+        // lower past higher
+        assert_eq!(
+            corrupt(&|buffer| set_u16(buffer, header_offsets.lower.0, 62)),
+            lower_error
+        );
+        // higher past the end of the page
+        assert_eq!(
+            corrupt(&|buffer| set_u16(buffer, header_offsets.higher.0, 65)),
+            Err(PageError::CorruptPage {
+                offset: header_offsets.higher.0,
+            })
+        );
+        // slot pointer into the free gap
+        assert_eq!(
+            corrupt(&|buffer| set_u16(buffer, 18, 30)),
+            Err(PageError::CorruptPage { offset: 18 })
+        );
+        // row whose text length runs past the end of the page
+        assert_eq!(
+            corrupt(&|buffer| set_u16(buffer, 53, 100)),
+            Err(PageError::CorruptPage { offset: 55 })
+        );
+    }
+
+    #[test]
+    fn test_write_and_read_decimal() {
+        let page = Page::new(0, 8192, None);
+        let values = [
+            (1234, 2, "12.34"),
+            (-5, 2, "-0.05"),
+            (100, 0, "100"),
+            (19_999, 3, "19.999"),
+        ];
+        let data_fields: Vec<_> = values
+            .iter()
+            .map(|&(mantissa, scale, _)| DataField::Decimal { mantissa, scale })
+            .collect();
+        assert_eq!(row_size(&data_fields[..1]) - row_size(&[]), 2 + 9);
+        page.write(&data_fields).unwrap();
+        let rows = page.read().unwrap();
+        assert_eq!(rows, vec![data_fields]);
+        for (field, (_, _, text)) in rows[0].iter().zip(values) {
+            assert_eq!(field.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_header_and_slot_pointers() {
+        let page = Page::new(4, 64, None);
+        page.write(&[DataField::Integer(1)]).unwrap();
+        page.write(&[DataField::Integer(2), DataField::Integer(3)])
+            .unwrap();
+        page.delete(0).unwrap();
+        page.seal();
+        let header = page.header();
+        assert_eq!(header.magic, Some(*MAGIC));
+        assert_eq!(header.version, FORMAT_VERSION);
+        assert_eq!(header.id, 4);
+        assert_eq!(header.end_headers, 18);
+        assert_eq!(header.lower, 18 + 2 * 2);
+        assert_eq!(header.next_page, Some(NO_NEXT_PAGE));
+        assert_eq!(header.higher, 64 - 6 - 10);
+        assert_eq!(
+            header.checksum,
+            Some(Page::compute_checksum(&page.get_buffer(), (14, 18)))
+        );
+        assert_eq!(page.slot_pointers(), vec![0, 64 - 6 - 10]);
+    }
+
+    #[test]
+    fn test_partial_cmp() {
+        assert!(DataField::Integer(3) < DataField::Integer(5));
+        assert!(DataField::Text("a".to_string()) < DataField::Text("b".to_string()));
+        assert!(DataField::Int64(-1) <= DataField::Int64(-1));
+        assert!(DataField::Float64(-0.5) > DataField::Float64(-1.0));
+        assert_eq!(
+            DataField::Integer(1).partial_cmp(&DataField::Text("1".to_string())),
+            None
+        );
+        assert_eq!(
+            DataField::Integer(1).partial_cmp(&DataField::Int32(1)),
+            None
+        );
+        assert_eq!(
+            DataField::Float64(f64::NAN).partial_cmp(&DataField::Float64(1.0)),
+            None
+        );
+        let decimal = |mantissa, scale| DataField::Decimal { mantissa, scale };
+        assert!(decimal(1234, 2) < decimal(12345, 3));
+        assert!(decimal(-5, 0) < decimal(1, 2));
+        assert_eq!(decimal(100, 2).partial_cmp(&decimal(1, 0)), None);
+    }
+
+    #[test]
+    fn test_write_reuses_deleted_slot() {
+        let page = Page::new(0, 8192, None);
+        for i in 0..3 {
+            page.write(&[DataField::Integer(i)]).unwrap();
+        }
+        page.delete(1).unwrap();
+        assert_eq!(page.next_slot(), 1);
+        let lower = page.read_metadata().lower;
+        page.write(&[DataField::Integer(9)]).unwrap();
+        assert_eq!(page.read_metadata().lower, lower);
+        assert_eq!(page.slot_count(), 3);
+        assert_eq!(page.get_row(1).unwrap(), vec![DataField::Integer(9)]);
+        assert_eq!(page.next_slot(), 3);
+    }
+
+    // Rebuilds `page` in an older header layout, keeping its row data where
+    // it is.
+    fn legacy_buffer(page: &Page, version: u8) -> Vec<u8> {
+        let offsets = PageHeaderOffsets::for_version(version).unwrap();
+        let header = page.header();
+        let current = page.get_buffer();
+        let mut buffer = vec![0u8; page.page_size];
+        if let Some(magic) = offsets.magic {
+            buffer[magic.0..magic.1].copy_from_slice(MAGIC);
+        }
+        if let Some(version_offset) = offsets.version {
+            buffer[version_offset] = version;
+        }
+        let pointers = page.slot_pointers();
+        let lower = offsets.end_headers + 2 * pointers.len() as u16;
+        buffer[offsets.id.0..offsets.id.1].copy_from_slice(&header.id.to_le_bytes());
+        buffer[offsets.lower.0..offsets.lower.1].copy_from_slice(&lower.to_le_bytes());
+        buffer[offsets.higher.0..offsets.higher.1].copy_from_slice(&header.higher.to_le_bytes());
+        for (i, pointer) in pointers.iter().enumerate() {
+            let offset = offsets.end_headers as usize + 2 * i;
+            buffer[offset..offset + 2].copy_from_slice(&pointer.to_le_bytes());
+        }
+        let higher = header.higher as usize;
+        buffer[higher..].copy_from_slice(&current[higher..]);
+        if let Some(checksum) = offsets.checksum {
+            let mut crc = Crc32::new();
+            crc.update(&buffer[..checksum.0]);
+            crc.update(&buffer[checksum.1..]);
+            let crc = crc.finish();
+            buffer[checksum.0..checksum.1].copy_from_slice(&crc.to_le_bytes());
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_reads_older_header_versions() {
+        let page = Page::new(4, 256, None);
+        page.write(&[DataField::Integer(1), DataField::Text("old".to_string())])
+            .unwrap();
+        page.write(&[DataField::Int64(-2)]).unwrap();
+        for version in [1, 2, 3] {
+            let old = Page::new(4, 256, Some(legacy_buffer(&page, version)));
+            old.check_format().unwrap();
+            old.verify().unwrap();
+            assert_eq!(old.header().version, version);
+            assert_eq!(old.header().lower, old.header().end_headers + 4);
+            assert_eq!(old.read().unwrap(), page.read().unwrap());
+            old.write(&[DataField::Bool(true)]).unwrap();
+            old.seal();
+            old.verify().unwrap();
+            assert_eq!(old.get_row(2).unwrap(), vec![DataField::Bool(true)]);
+        }
+        assert_eq!(
+            Page::new(4, 256, Some(legacy_buffer(&page, 1)))
+                .header()
+                .magic,
+            None
+        );
+    }
+
+    #[test]
+    fn test_distinct() {
+        let page = Page::new(0, 256, None);
+        for value in [3, 1, 3, 2, 1] {
+            page.write(&[
+                DataField::Text("row".to_string()),
+                DataField::Integer(value),
+            ])
+            .unwrap();
+        }
+        page.delete(3).unwrap();
+        assert_eq!(
+            page.distinct(1).unwrap(),
+            vec![DataField::Integer(3), DataField::Integer(1)]
+        );
+        assert_eq!(page.distinct(2), Err(PageError::FieldOutOfRange(2)));
+    }
+
+    #[test]
+    fn test_try_write_until_full() {
+        let page = Page::new(0, 256, None);
+        let row = [DataField::Integer(7), DataField::Int64(-1)];
+        let mut slots = Vec::new();
+        while let Some(slot) = page.try_write(&row) {
+            slots.push(slot);
+        }
+        assert_eq!(slots, (0..slots.len()).collect::<Vec<_>>());
+        // Each row takes 16 bytes plus its 2-byte pointer.
+        assert_eq!(slots.len(), (256 - 18) / 18);
+        assert!(page.free_space() < 18);
+        assert_eq!(page.read().unwrap(), vec![row.to_vec(); slots.len()]);
+        page.delete(2).unwrap();
+        assert_eq!(page.try_write(&[DataField::Null]), Some(2));
+    }
+
+    #[test]
+    fn test_merge_from() {
+        let target = Page::new(0, 256, None);
+        let source = Page::new(1, 256, None);
+        let row = |i| vec![DataField::Integer(i), DataField::Int64(i as i64)];
+        for i in 0..5 {
+            target.write(&row(i)).unwrap();
+            source.write(&row(100 + i)).unwrap();
+        }
+        source.delete(1).unwrap();
+        assert_eq!(target.merge_from(&source).unwrap(), 4);
+        let mut expected: Vec<_> = (0..5).map(row).collect();
+        expected.extend([100, 102, 103, 104].map(row));
+        assert_eq!(target.read().unwrap(), expected);
+        assert_eq!(source.count(), 0);
+        assert_eq!(source.free_space(), 256 - 18);
+
+        // Only part of a full page fits.
+        let full = Page::new(2, 256, None);
+        while full.try_write(&row(7)).is_some() {}
+        let moved = target.merge_from(&full).unwrap();
+        assert!(moved > 0 && moved < (256 - 18) / 18);
+        assert_eq!(target.count() + full.count(), 9 + (256 - 18) / 18);
+    }
+
+    #[test]
+    fn test_any_stops_at_first_match() {
+        let page = Page::new(0, 256, None);
+        for i in 0..3 {
+            page.write(&[DataField::Integer(i)]).unwrap();
+        }
+        let mut buffer = page.get_buffer().clone();
+        let data_offset = page.slot_pointers()[2] as usize;
+        buffer[data_offset + 2..data_offset + 4].copy_from_slice(&99u16.to_le_bytes());
+        let corrupt_page = Page::new(0, 256, Some(buffer));
+        assert_eq!(
+            corrupt_page.any(|row| row[0] == DataField::Integer(1)),
+            Ok(true)
+        );
+        assert_eq!(
+            corrupt_page.any(|row| row[0] == DataField::Integer(5)),
+            Err(PageError::UnknownType(99))
+        );
+        assert_eq!(page.any(|row| row[0] == DataField::Integer(5)), Ok(false));
+    }
+
+    #[test]
+    fn test_snapshot_ignores_later_writes() {
+        let page = Page::new(3, 256, None);
+        page.write(&[DataField::Integer(1)]).unwrap();
+        let snapshot = page.snapshot();
+        page.write(&[DataField::Integer(2)]).unwrap();
+        page.update(0, &[DataField::Integer(9)]).unwrap();
+        assert_eq!(snapshot.id(), 3);
+        assert_eq!(snapshot.read().unwrap(), vec![vec![DataField::Integer(1)]]);
+        assert_eq!(snapshot.count(), 1);
+        assert_eq!(snapshot.get_row(1), Err(PageError::InvalidSlot(1)));
+        assert_eq!(page.count(), 2);
+    }
+
+    #[test]
+    fn test_write_and_read_char() {
+        let page = Page::new(0, 256, None);
+        let data_fields = vec![DataField::Char('a'), DataField::Char('\u{20ac}')];
+        assert_eq!(row_size(&data_fields[..1]) - row_size(&[]), 2 + 4);
+        page.write(&data_fields).unwrap();
+        assert_eq!(page.read().unwrap(), vec![data_fields]);
+
+        let mut buffer = page.get_buffer().clone();
+        let data_offset = page.slot_pointers()[0] as usize;
+        // Skips the field count and both tags to reach the first payload.
+        buffer[data_offset + 6..data_offset + 10].copy_from_slice(&0xD800u32.to_le_bytes());
+        let corrupt_page = Page::new(0, 256, Some(buffer));
+        assert_eq!(
+            corrupt_page.read(),
+            Err(PageError::InvalidChar {
+                slot: 0,
+                value: 0xD800
+            })
+        );
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let page = Page::new(0, 256, None);
+        for i in 0..4 {
+            page.write(&[DataField::Integer(i)]).unwrap();
+        }
+        let rows = vec![
+            vec![DataField::Text("new".to_string())],
+            vec![DataField::Bool(true), DataField::Null],
+        ];
+        page.rewrite(&rows).unwrap();
+        assert_eq!(page.read().unwrap(), rows);
+        assert_eq!(page.slot_pointers().len(), 2);
+
+        let too_many = vec![vec![DataField::Bytes(vec![0; 100])]; 3];
+        assert_eq!(page.rewrite(&too_many), Err(PageError::PageFull));
+        assert_eq!(page.read().unwrap(), rows);
+    }
+
+    #[test]
+    fn test_defragment() {
+        let page = Page::new(0, 256, None);
+        for i in 0..6 {
+            page.write(&[DataField::Integer(i), DataField::Text("x".repeat(10))])
+                .unwrap();
+        }
+        page.delete(1).unwrap();
+        page.delete(4).unwrap();
+        let before = page.get_buffer().clone();
+        let copy = page.defragment().unwrap();
+        assert_eq!(*page.get_buffer(), before);
+        assert!(copy.free_space() > page.free_space());
+        assert_eq!(copy.stats().unwrap().dead_bytes, 0);
+        assert_eq!(copy.read().unwrap(), page.read().unwrap());
+    }
+
+    #[test]
+    fn test_write_and_read_array() {
+        let page = Page::new(0, 256, None);
+        let integers = DataField::Array(vec![
+            DataField::Integer(1),
+            DataField::Integer(2),
+            DataField::Integer(3),
+        ]);
+        let mixed = DataField::Array(vec![
+            DataField::Text("a".to_string()),
+            DataField::Null,
+            DataField::Array(vec![DataField::Bool(true)]),
+        ]);
+        assert_eq!(
+            row_size(std::slice::from_ref(&integers)) - row_size(&[]),
+            2 + 2 + 3 * 4
+        );
+        let data_fields = vec![integers, mixed, DataField::Int64(-1)];
+        page.write(&data_fields).unwrap();
+        assert_eq!(page.free_space(), 256 - 18 - 2 - row_size(&data_fields));
+        assert_eq!(page.read().unwrap(), vec![data_fields.clone()]);
+        assert_eq!(
+            page.read_projected(&[2]).unwrap(),
+            vec![vec![DataField::Int64(-1)]]
+        );
+        assert_eq!(data_fields[1].to_string(), "[a, , [true]]");
+    }
+
+    #[test]
+    fn test_split_off() {
+        let page = Page::new(0, 256, None);
+        let row = |i| vec![DataField::Integer(i), DataField::Int64(-(i as i64))];
+        let mut i = 0;
+        while page.try_write(&row(i)).is_some() {
+            i += 1;
+        }
+        let upper = page.split_off(1).unwrap();
+        upper.write(&row(i)).unwrap();
+        for split in [&page, &upper] {
+            split.validate().unwrap();
+            assert_eq!(split.stats().unwrap().dead_bytes, 0);
+        }
+        assert_eq!(upper.id(), 1);
+        assert_eq!(page.count(), i as usize / 2);
+        let mut rows = page.read().unwrap();
+        rows.extend(upper.read().unwrap());
+        assert_eq!(rows, (0..=i).map(row).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_split_off_keeps_filter_and_packed_rows() {
+        let page = Page::with_bloom_filter(0, 1024, 0);
+        let empty_free_space = page.free_space();
+        let mut i = 0;
+        while page.write_ints(&[i, 1, 2, 3]).is_ok() {
+            i += 1;
+        }
+        let upper = page.split_off(1).unwrap();
+        for (split, first, count) in [(&page, 0, i / 2), (&upper, i / 2, i - i / 2)] {
+            assert_eq!(split.bloom_field(), Some(0));
+            // Packed rows take 2 bytes of count and 8 of ints, plus a pointer.
+            assert_eq!(split.free_space(), empty_free_space - 12 * count as usize);
+            assert_eq!(
+                split.read_ints().unwrap(),
+                (first..first + count)
+                    .map(|i| vec![i, 1, 2, 3])
+                    .collect::<Vec<_>>()
+            );
+            assert!(split.might_contain(0, &DataField::Integer(first)));
+        }
+    }
+
+    #[test]
+    fn test_checked_bytes_detects_corruption() {
+        let page = Page::new(0, 256, None);
+        let blob = DataField::CheckedBytes((0..50).collect());
+        assert_eq!(
+            row_size(std::slice::from_ref(&blob)) - row_size(&[]),
+            2 + 56
+        );
+        page.write(&[DataField::Integer(1)]).unwrap();
+        page.write(&[DataField::Integer(2), blob.clone()]).unwrap();
+        assert_eq!(page.get_row(1).unwrap()[1], blob);
+        assert_eq!(
+            page.read_projected(&[0]).unwrap()[1],
+            vec![DataField::Integer(2)]
+        );
+
+        let mut buffer = page.get_buffer().clone();
+        // Past the field count, two tags, the integer, the length and the CRC.
+        let blob_offset = page.slot_pointers()[1] as usize + 6 + 2 + 6;
+        buffer[blob_offset + 10] ^= 0xFF;
+        let corrupt_page = Page::new(0, 256, Some(buffer));
+        assert_eq!(
+            corrupt_page.read(),
+            Err(PageError::BlobChecksumMismatch { slot: 1 })
+        );
+        assert_eq!(
+            corrupt_page.get_row(0).unwrap(),
+            vec![DataField::Integer(1)]
+        );
+    }
+
+    #[test]
+    fn test_estimated_remaining_rows() {
+        let page = Page::new(0, 256, None);
+        let row = [DataField::Integer(1), DataField::Int64(2)];
+        let estimate = page.estimated_remaining_rows(row_size(&row));
+        assert_eq!(estimate, (256 - 18) / 18);
+        let mut written = 0;
+        while page.try_write(&row).is_some() {
+            written += 1;
+        }
+        assert_eq!(written, estimate);
+        assert_eq!(page.estimated_remaining_rows(row_size(&row)), 0);
+        assert_eq!(page.estimated_remaining_rows(0), page.free_space() / 2);
+    }
+
+    #[test]
+    fn test_write_at_exact_boundary() {
+        // An empty 256-byte page has 238 free bytes. A `Bytes(n)` row takes
+        // n + 6 bytes plus its 2-byte pointer, so n = 230 fills it exactly.
+        let page = Page::new(0, 256, None);
+        let before = page.get_buffer().clone();
+        assert_eq!(
+            page.write(&[DataField::Bytes(vec![7; 231])]),
+            Err(PageError::PageFull)
+        );
+        assert_eq!(*page.get_buffer(), before);
+        page.write(&[DataField::Bytes(vec![7; 230])]).unwrap();
+        assert_eq!(page.free_space(), 0);
+        let header = page.header();
+        assert_eq!(header.lower, header.higher);
+        assert_eq!(page.slot_pointers(), vec![header.higher]);
+        assert_eq!(
+            page.get_row(0).unwrap(),
+            vec![DataField::Bytes(vec![7; 230])]
+        );
+        assert_eq!(page.write(&[]), Err(PageError::PageFull));
+        page.delete(0).unwrap();
+        assert_eq!(page.write(&[]), Err(PageError::PageFull));
+    }
+
+    #[test]
+    fn test_write_and_read_json() {
+        let page = Page::new(0, 256, None);
+        let document = JsonValue::parse(r#"{"id":7,"tags":["a",{"deep":null}]}"#).unwrap();
+        let data_fields = vec![DataField::Json(document), DataField::Integer(1)];
+        page.write(&data_fields).unwrap();
+        assert_eq!(page.read().unwrap(), vec![data_fields]);
+        assert_eq!(
+            page.read_projected(&[1]).unwrap(),
+            vec![vec![DataField::Integer(1)]]
+        );
+
+        let mut buffer = page.get_buffer().clone();
+        // Past the field count, both tags and the length prefix lies the `{`.
+        let text_offset = page.slot_pointers()[0] as usize + 6 + 4;
+        assert_eq!(buffer[text_offset], b'{');
+        buffer[text_offset] = b'[';
+        let corrupt_page = Page::new(0, 256, Some(buffer));
+        assert_eq!(corrupt_page.read(), Err(PageError::InvalidJson { slot: 0 }));
+    }
+
+    #[test]
+    fn test_read_range() {
+        let page = Page::new(0, 256, None);
+        for i in 0..10 {
+            page.write(&[DataField::Integer(i)]).unwrap();
+        }
+        let rows = page.read().unwrap();
+        assert_eq!(page.read_range(3, 6).unwrap(), rows[3..6]);
+        assert_eq!(page.read_range(8, 100).unwrap(), rows[8..]);
+        assert_eq!(
+            page.read_range(12, 20).unwrap(),
+            Vec::<Vec<DataField>>::new()
+        );
+        assert_eq!(page.read_range(5, 4), Err(PageError::InvalidSlot(5)));
+        page.delete(4).unwrap();
+        assert_eq!(
+            page.read_range(3, 6).unwrap(),
+            vec![rows[3].clone(), rows[5].clone()]
+        );
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let page = Page::with_bloom_filter(0, 1024, 1);
+        assert_eq!(page.bloom_field(), Some(1));
+        assert_eq!(page.free_space(), 1024 - 18 - 2 - 32);
+        let row = |i: u16| vec![DataField::Text("row".to_string()), DataField::Integer(i)];
+        for i in 0..30 {
+            page.write(&row(i * 7)).unwrap();
+        }
+        let present =
+            |page: &Page| (0..30).all(|i| page.might_contain(1, &DataField::Integer(i * 7)));
+        assert!(present(&page));
+        assert!((1000..1100).any(|i| !page.might_contain(1, &DataField::Integer(i))));
+        // Other fields and types are never ruled out by the filter.
+        assert!(page.might_contain(0, &DataField::Text("absent".to_string())));
+        assert!(Page::new(0, 1024, None).might_contain(1, &DataField::Integer(1)));
+
+        let reopened = Page::new(0, 1024, Some(page.get_buffer().clone()));
+        reopened.check_format().unwrap();
+        assert!(present(&reopened));
+        page.update(0, &row(5000)).unwrap();
+        assert!(page.might_contain(1, &DataField::Integer(5000)));
+        // Compacting rebuilds the filter from the live rows, so the replaced
+        // value is dropped from it.
+        page.compact().unwrap();
+        assert!(page.might_contain(1, &DataField::Integer(5000)));
+        assert!((1..30).all(|i| page.might_contain(1, &DataField::Integer(i * 7))));
+        let stats = page.stats().unwrap();
+        assert_eq!(stats.used_bytes + stats.free_bytes + stats.dead_bytes, 1024);
+        page.clear();
+        assert_eq!(page.free_space(), 1024 - 18 - 2 - 32);
+        assert!(!present(&page));
+    }
+
+    #[test]
+    fn test_latin1_text_encoding() {
+        let page = Page::with_text_encoding(0, 256, TextEncoding::Latin1);
+        page.write(&[
+            DataField::Text("caf\u{e9}".to_string()),
+            DataField::LargeText("\u{ff}".to_string()),
+        ])
+        .unwrap();
+        let mut buffer = page.get_buffer().clone();
+        assert!(buffer.windows(4).any(|window| window == b"caf\xE9"));
+        let reopened = Page::new(0, 256, Some(buffer.clone()));
+        assert_eq!(reopened.text_encoding(), TextEncoding::Latin1);
+        assert_eq!(
+            reopened.read().unwrap(),
+            vec![vec![
+                DataField::Text("caf\u{e9}".to_string()),
+                DataField::LargeText("\u{ff}".to_string()),
+            ]]
+        );
+        buffer[page.header_offsets.flags.unwrap()] &= !FLAG_LATIN1;
+        let as_utf8 = Page::new(0, 256, Some(buffer));
+        assert_eq!(as_utf8.read(), Err(PageError::InvalidUtf8 { slot: 0 }));
+        assert_eq!(
+            page.write(&[DataField::Text("\u{20ac}".to_string())]),
+            Err(PageError::UnencodableText { value: '\u{20ac}' })
+        );
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let page = Page::new(4, 256, None);
+        page.write(&[DataField::Integer(1), DataField::Text("sent".to_string())])
+            .unwrap();
+        page.write(&[DataField::Null]).unwrap();
+        let bytes = page.to_bytes();
+        let received = Page::from_bytes(4, 256, bytes.clone()).unwrap();
+        assert_eq!(received.read().unwrap(), page.read().unwrap());
+        assert_eq!(
+            Page::from_bytes(4, 512, bytes.clone()).err(),
+            Some(PageError::WrongPageSize {
+                expected: 512,
+                found: 256
+            })
+        );
+        let mut corrupt = bytes;
+        corrupt[255] ^= 0xFF;
+        assert_eq!(
+            Page::from_bytes(4, 256, corrupt).err(),
+            Some(PageError::ChecksumMismatch)
+        );
+    }
+
+    #[test]
+    fn test_try_write_batch_does_not_block() {
+        let page = Page::new(0, 256, None);
+        let rows = vec![vec![DataField::Integer(1)], vec![DataField::Integer(2)]];
+        let guard = page.get_buffer();
+        std::thread::scope(|scope| {
+            let pending = scope.spawn(|| page.try_write_batch(&rows));
+            assert_eq!(pending.join().unwrap(), Ok(None));
+        });
+        drop(guard);
+        assert_eq!(page.try_write_batch(&rows), Ok(Some(2)));
+        assert_eq!(page.read().unwrap(), rows);
+    }
+
+    #[test]
+    fn test_write_and_read_int16() {
+        let page = Page::new(0, 256, None);
+        let data_fields = vec![
+            DataField::Int16(-1),
+            DataField::Int16(i16::MIN),
+            DataField::Int16(i16::MAX),
+            DataField::Integer(u16::MAX),
+        ];
+        page.write(&data_fields).unwrap();
+        assert_eq!(page.read().unwrap(), vec![data_fields]);
+        assert_ne!(DataField::Int16(-1), DataField::Integer(u16::MAX));
+        assert!(DataField::Int16(-1) < DataField::Int16(1));
+    }
+
+    #[test]
+    fn test_set_id() {
+        let page = Page::new(0, 256, None);
+        page.write(&[DataField::Integer(1)]).unwrap();
+        page.set_id(7);
+        assert_eq!(page.id(), 7);
+        assert_eq!(page.snapshot().id(), 7);
+        let reopened = Page::from_bytes(7, 256, page.to_bytes()).unwrap();
+        assert_eq!(reopened.read_metadata().id, 7);
+        assert_eq!(reopened.header().id, 7);
+        assert_eq!(reopened.read().unwrap(), vec![vec![DataField::Integer(1)]]);
+    }
+
+    #[test]
+    fn test_next_page_chain() {
+        let first = Page::new(0, 256, None);
+        let second = Page::new(1, 256, None);
+        assert_eq!(first.next(), None);
+        first.set_next(Some(second.id())).unwrap();
+        let pages = [
+            Page::from_bytes(0, 256, first.to_bytes()).unwrap(),
+            Page::from_bytes(1, 256, second.to_bytes()).unwrap(),
+        ];
+        let mut chain = vec![0];
+        while let Some(next_page) = pages[*chain.last().unwrap() as usize].next() {
+            chain.push(next_page);
+        }
+        assert_eq!(chain, vec![0, 1]);
+        first.set_next(None).unwrap();
+        assert_eq!(first.next(), None);
+        let old = Page::new(0, 256, Some(legacy_buffer(&first, 3)));
+        assert_eq!(old.next(), None);
+        assert_eq!(old.set_next(Some(1)), Err(PageError::UnsupportedVersion(3)));
+    }
+
+    #[test]
+    fn test_write_and_read_ints() {
+        let page = Page::new(0, 256, None);
+        page.write_ints(&[1, 2, 3]).unwrap();
+        page.write(&[DataField::Integer(4), DataField::Integer(5)])
+            .unwrap();
+        page.write_ints(&[]).unwrap();
+        assert_eq!(page.free_space(), 256 - 18 - 3 * 2 - 8 - 10 - 2);
+        assert_eq!(
+            page.read_ints().unwrap(),
+            vec![vec![1, 2, 3], vec![4, 5], vec![]]
+        );
+        assert_eq!(
+            page.get_row(0).unwrap(),
+            vec![
+                DataField::Integer(1),
+                DataField::Integer(2),
+                DataField::Integer(3)
+            ]
+        );
+        assert_eq!(page.get_row(2).unwrap(), vec![]);
+        assert_eq!(page.stats().unwrap().dead_bytes, 0);
+        page.update(0, &[DataField::Integer(9)]).unwrap();
+        assert_eq!(page.get_row(1).unwrap()[0], DataField::Integer(4));
+        page.write(&[DataField::Integer(6), DataField::Null])
+            .unwrap();
+        assert_eq!(
+            page.read_ints(),
+            Err(PageError::TypeMismatch {
+                column: "1".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_field_at() {
+        let page = Page::new(0, 256, None);
+        page.write(&[DataField::Integer(1)]).unwrap();
+        page.write(&[
+            DataField::Text("skipped".to_string()),
+            DataField::Array(vec![DataField::Null, DataField::Int64(2)]),
+            DataField::Float64(2.5),
+        ])
+        .unwrap();
+        assert_eq!(page.field_at(1, 2).unwrap(), page.read().unwrap()[1][2]);
+        assert_eq!(page.field_at(1, 2).unwrap(), DataField::Float64(2.5));
+        assert_eq!(page.field_at(0, 0).unwrap(), DataField::Integer(1));
+        assert_eq!(page.field_at(1, 3), Err(PageError::FieldOutOfRange(3)));
+        page.delete(0).unwrap();
+        assert_eq!(page.field_at(0, 0), Err(PageError::InvalidSlot(0)));
+    }
+
+    #[test]
+    fn test_write_rejects_bytes_over_u16_length() {
+        let page = Page::new(0, 8192, None);
+        for field in [
+            DataField::Bytes(vec![0; 70_000]),
+            DataField::CheckedBytes(vec![0; 70_000]),
+        ] {
+            assert_eq!(
+                Page::encode_row(&[field], Endianness::Little),
+                Err(PageError::BytesTooLong { len: 70_000 })
+            );
+        }
+        assert_eq!(
+            page.write(&[DataField::Bytes(vec![0; 70_000])]),
+            Err(PageError::BytesTooLong { len: 70_000 })
+        );
+    }
+
+    #[test]
+    fn test_compact_full_page_of_packed_ints() {
+        let page = Page::new(0, 256, None);
+        let ints: Vec<u16> = (0..10).collect();
+        let mut rows = Vec::new();
+        while page.write_ints(&ints).is_ok() {
+            rows.push(ints.clone());
+        }
+        page.delete(0).unwrap();
+        rows.remove(0);
+        let free_space = page.free_space();
+        page.compact().unwrap();
+        assert_eq!(page.read_ints().unwrap(), rows);
+        // The deleted row's data and its slot pointer are both reclaimed.
+        assert_eq!(page.free_space(), free_space + 2 + 2 * ints.len() + 2);
+        assert_eq!(page.stats().unwrap().dead_bytes, 0);
+    }
+
+    #[test]
+    fn test_compact_leaves_page_intact_on_error() {
+        let page = Page::new(0, 64, None);
+        page.write(&[DataField::Integer(1)]).unwrap();
+        let mut buffer = page.get_buffer().clone();
+        // Give the row an unknown type tag so it cannot be decoded.
+        let pointer = page.slot_pointers()[0] as usize;
+        buffer[pointer + 2..pointer + 4].copy_from_slice(&999u16.to_le_bytes());
+        let corrupt = Page::new(0, 64, Some(buffer.clone()));
+        assert_eq!(corrupt.compact(), Err(PageError::UnknownType(999)));
+        assert_eq!(*corrupt.get_buffer(), buffer);
+    }
+
+    #[test]
+    fn test_deeply_nested_json_is_rejected() {
+        let page = Page::new(0, 4096, None);
+        let mut document = JsonValue::Null;
+        for _ in 0..MAX_DEPTH + 1 {
+            document = JsonValue::Array(vec![document]);
+        }
+        assert_eq!(
+            page.write(&[DataField::Json(document)]),
+            Err(PageError::JsonTooDeep)
+        );
+
+        // The quoted string is exactly as long as MAX_DEPTH + 1 nested arrays.
+        let shallow = JsonValue::String("x".repeat(2 * MAX_DEPTH));
+        page.write(&[DataField::Json(shallow)]).unwrap();
+        let mut buffer = page.get_buffer().clone();
+        let text_offset = page.slot_pointers()[0] as usize + 4 + 4;
+        let depth = MAX_DEPTH + 1;
+        buffer[text_offset..text_offset + 2 * depth]
+            .copy_from_slice(("[".repeat(depth) + &"]".repeat(depth)).as_bytes());
+        let corrupt_page = Page::new(0, 4096, Some(buffer));
+        assert_eq!(corrupt_page.read(), Err(PageError::InvalidJson { slot: 0 }));
+    }
+
+    #[test]
+    fn test_concurrent_updates_do_not_overlap() {
+        let page = Arc::new(Page::new(0, 8192, None));
+        let num_threads = 8;
+        for _ in 0..num_threads {
+            page.write(&[DataField::Null]).unwrap();
+        }
+        let handles: Vec<_> = (0..num_threads)
+            .map(|slot| {
+                let page = Arc::clone(&page);
+                std::thread::spawn(move || {
+                    for len in 1..=20 {
+                        let text = char::from(b'a' + slot as u8).to_string().repeat(len);
+                        page.update(slot, &[DataField::Text(text)]).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        for slot in 0..num_threads {
+            let text = char::from(b'a' + slot as u8).to_string().repeat(20);
+            assert_eq!(page.get_row(slot).unwrap(), vec![DataField::Text(text)]);
+        }
+    }
+
+    #[test]
+    fn test_bloom_filter_on_small_pages() {
+        // Under 32 bytes a page gets no filter bits, so it gets no filter.
+        for page_size in [20, 31] {
+            let page = Page::with_bloom_filter(0, page_size, 0);
+            assert_eq!(page.bloom_field(), None);
+            assert_eq!(page.free_space(), page_size - 18);
+            assert!(page.might_contain(0, &DataField::Bool(false)));
+        }
+        let page = Page::with_bloom_filter(0, 31, 0);
+        page.write(&[DataField::Bool(true)]).unwrap();
+        assert_eq!(page.read().unwrap(), vec![vec![DataField::Bool(true)]]);
+
+        let page = Page::with_bloom_filter(0, 32, 0);
+        assert_eq!(page.bloom_field(), Some(0));
+        page.write(&[DataField::Bool(true)]).unwrap();
+        assert!(page.might_contain(0, &DataField::Bool(true)));
+    }
+
+    #[test]
+    fn test_encode_rejects_too_many_fields() {
+        let page = Page::new(0, 256, None);
+        let nested = vec![DataField::Array(vec![DataField::Null; MAX_FIELDS + 1])];
+        assert_eq!(
+            Page::encode_row(&nested, Endianness::Little),
+            Err(PageError::TooManyFields {
+                count: MAX_FIELDS + 1
+            })
+        );
+        assert_eq!(
+            page.write(&vec![DataField::Null; MAX_FIELDS + 1]),
+            Err(PageError::TooManyFields {
+                count: MAX_FIELDS + 1
+            })
+        );
+        assert!(Page::encode_row(&vec![DataField::Null; MAX_FIELDS], Endianness::Little).is_ok());
+    }
+
+    #[test]
+    fn test_arrays_nested_past_the_cap_are_rejected() {
+        let mut nested = vec![DataField::Null];
+        for _ in 0..MAX_ARRAY_DEPTH {
+            nested = vec![DataField::Array(nested)];
+        }
+        let row = Page::encode_row(&nested, Endianness::Little).unwrap();
+        assert_eq!(
+            Page::decode_row(&row, 0, 0, Endianness::Little).unwrap(),
+            nested
+        );
+        assert_eq!(
+            Page::encode_row(&[DataField::Array(nested)], Endianness::Little),
+            Err(PageError::ArrayTooDeep)
+        );
+
+        // Each level is a count of 1 and an `Array` tag, far past the cap.
+        let depth = 200_000;
+        let mut row = [1u8, 0, 17, 0].repeat(depth);
+        row.extend_from_slice(&[0, 0]);
+        assert!(matches!(
+            Page::decode_row(&row, 0, 0, Endianness::Little),
+            Err(PageError::CorruptPage { .. })
+        ));
+        let mut offset = 2;
+        assert!(matches!(
+            Page::skip_field(&row, &mut offset, 17, Endianness::Little),
+            Err(PageError::CorruptPage { .. })
+        ));
+    }
+
+    #[test]
+    fn test_page_view_reads_borrowed_buffer() {
+        let page = Page::with_endianness(0, 256, Endianness::Big);
+        page.write(&[DataField::Integer(1), DataField::Text("a".to_string())])
+            .unwrap();
+        page.write(&[DataField::Null]).unwrap();
+        page.delete(1).unwrap();
+        page.seal();
+        let buffer = page.get_buffer().clone();
+        let view = PageView::new(&buffer, true).unwrap();
+        assert_eq!(view.read().unwrap(), page.read().unwrap());
+        assert!(!view.is_overflow());
+
+        let mut corrupt = buffer.clone();
+        corrupt[200] ^= 1;
+        assert_eq!(
+            PageView::new(&corrupt, true).err(),
+            Some(PageError::ChecksumMismatch)
+        );
+        assert!(PageView::new(&corrupt, false).is_ok());
+        assert_eq!(
+            PageView::new(&buffer[..4], true).err(),
+            Some(PageError::CorruptPage { offset: 4 })
+        );
+    }
+
+    #[test]
+    fn test_defragment_keeps_packed_rows() {
+        let page = Page::new(0, 8192, None);
+        while page.write_ints(&[1, 2, 3, 4]).is_ok() {}
+        let num_rows = page.slot_count();
+        page.delete(0).unwrap();
+        let defragmented = page.defragment().unwrap();
+        assert_eq!(defragmented.slot_count(), num_rows - 1);
+        assert_eq!(
+            defragmented.read_ints().unwrap(),
+            vec![vec![1, 2, 3, 4]; num_rows - 1]
+        );
+        assert_eq!(page.slot_count(), num_rows);
+        defragmented.write_ints(&[5, 6, 7, 8]).unwrap();
+    }
+
+    #[test]
+    fn test_decode_payload_names_the_overflow_chain_on_bad_crc() {
+        let mut payload = Page::encode_payload(&DataField::CheckedBytes(vec![1, 2, 3])).unwrap();
+        *payload.last_mut().unwrap() ^= 0xFF;
+        assert_eq!(
+            Page::decode_payload(18, &payload, 7),
+            Err(PageError::OverflowChecksumMismatch { page_id: 7 })
+        );
     }
 }
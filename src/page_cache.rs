@@ -0,0 +1,80 @@
+use crate::page::Page;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// Bounded cache of pages keyed by page id that evicts the least recently
+/// used page once more than `capacity` pages are held.
+pub struct PageCache {
+    capacity: usize,
+    pages: HashMap<u16, Arc<Page>>,
+    recency: VecDeque<u16>,
+}
+
+impl PageCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            pages: HashMap::new(),
+            recency: VecDeque::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    pub fn contains(&self, page_id: u16) -> bool {
+        self.pages.contains_key(&page_id)
+    }
+
+    pub fn get(&mut self, page_id: u16) -> Option<Arc<Page>> {
+        let page = self.pages.get(&page_id).cloned()?;
+        self.touch(page_id);
+        Some(page)
+    }
+
+    pub fn insert(&mut self, page_id: u16, page: Arc<Page>) {
+        self.pages.insert(page_id, page);
+        self.touch(page_id);
+        while self.pages.len() > self.capacity {
+            match self.recency.pop_front() {
+                Some(evicted) => {
+                    self.pages.remove(&evicted);
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn touch(&mut self, page_id: u16) {
+        self.recency.retain(|&id| id != page_id);
+        self.recency.push_back(page_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = PageCache::new(2);
+        for page_id in 0..2 {
+            cache.insert(page_id, Arc::new(Page::new(page_id, 64, None)));
+        }
+        assert!(cache.get(0).is_some());
+        cache.insert(2, Arc::new(Page::new(2, 64, None)));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(0));
+        assert!(!cache.contains(1));
+        assert!(cache.contains(2));
+    }
+}
@@ -1,3 +1,51 @@
-use std::sync::{Arc, RwLock};
+use crate::page::{DataField, Page, PageError};
 
-pub struct PageScanner {}
+pub struct PageScanner<'a> {
+    page: &'a Page,
+}
+
+impl<'a> PageScanner<'a> {
+    pub fn new(page: &'a Page) -> Self {
+        Self { page }
+    }
+
+    pub fn filter<F: Fn(&[DataField]) -> bool>(
+        &self,
+        pred: F,
+    ) -> Result<Vec<Vec<DataField>>, PageError> {
+        let mut rows = Vec::new();
+        for row in self.page.iter() {
+            let row = row?;
+            if pred(&row) {
+                rows.push(row);
+            }
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let page = Page::new(0, 8192, None);
+        for i in 0..10 {
+            page.write(&[DataField::Integer(i % 3), DataField::Integer(i)])
+                .unwrap();
+        }
+        let scanner = PageScanner::new(&page);
+        let rows = scanner
+            .filter(|row| row[0] == DataField::Integer(1))
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec![DataField::Integer(1), DataField::Integer(1)],
+                vec![DataField::Integer(1), DataField::Integer(4)],
+                vec![DataField::Integer(1), DataField::Integer(7)],
+            ]
+        );
+    }
+}
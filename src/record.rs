@@ -0,0 +1,155 @@
+use crate::page::{DataField, Endianness, Page};
+use crate::{Result, StorageError, PAGE_SIZE};
+use std::io::{ErrorKind, Read};
+
+/// Serializes a row as a record: its length as a little-endian `u32`
+/// followed by the row encoded as `Page::write` stores it.
+pub fn frame_record(data_fields: &[DataField]) -> Result<Vec<u8>> {
+    let row = Page::encode_row(data_fields, Endianness::Little)?;
+    let mut record = Vec::with_capacity(4 + row.len());
+    record.extend_from_slice(&(row.len() as u32).to_le_bytes());
+    record.extend_from_slice(&row);
+    Ok(record)
+}
+
+/// Decodes records written by `frame_record` from any reader, one at a time.
+/// A reader that ends between records finishes the iterator; one that ends
+/// inside a record yields an `UnexpectedEof` error.
+///
+/// A length prefix over the reader's limit yields `RecordTooLong` without
+/// allocating for it, so corrupt input cannot exhaust memory.
+pub struct RecordReader<R: Read> {
+    reader: R,
+    buffer: Vec<u8>,
+    max_len: usize,
+}
+
+impl<R: Read> RecordReader<R> {
+    /// Reads records of at most `PAGE_SIZE` bytes, the most a stored row takes.
+    pub fn new(reader: R) -> Self {
+        Self::with_max_len(reader, PAGE_SIZE)
+    }
+
+    /// Reads records of at most `max_len` bytes.
+    pub fn with_max_len(reader: R, max_len: usize) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            max_len,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_record(&mut self) -> Result<Option<Vec<DataField>>> {
+        let mut len = [0u8; 4];
+        let mut filled = 0;
+        while filled < len.len() {
+            match self.reader.read(&mut len[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into()),
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len > self.max_len {
+            return Err(StorageError::RecordTooLong {
+                len,
+                max_len: self.max_len,
+            });
+        }
+        self.buffer.resize(len, 0);
+        self.reader.read_exact(&mut self.buffer)?;
+        Ok(Some(Page::decode_row(
+            &self.buffer,
+            0,
+            0,
+            Endianness::Little,
+        )?))
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = Result<Vec<DataField>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page::PageError;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_reads_framed_records() {
+        let rows = vec![
+            vec![DataField::Integer(1), DataField::Text("one".to_string())],
+            vec![],
+            vec![DataField::Bytes(vec![1, 2, 3]), DataField::Null],
+        ];
+        let mut bytes = Vec::new();
+        for row in &rows {
+            bytes.extend(frame_record(row).unwrap());
+        }
+        let decoded = RecordReader::new(Cursor::new(&bytes))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(decoded, rows);
+
+        let mut reader = RecordReader::new(Cursor::new(&bytes[..bytes.len() - 1]));
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(
+            reader.next(),
+            Some(Err(StorageError::Io(err))) if err.kind() == ErrorKind::UnexpectedEof
+        ));
+    }
+
+    #[test]
+    fn test_rejects_oversized_length_prefix() {
+        let mut bytes = u32::MAX.to_le_bytes().to_vec();
+        bytes.extend([0; 8]);
+        let mut reader = RecordReader::new(Cursor::new(&bytes));
+        assert!(matches!(
+            reader.next(),
+            Some(Err(StorageError::RecordTooLong { len, max_len: PAGE_SIZE }))
+                if len == u32::MAX as usize
+        ));
+
+        let record = frame_record(&[DataField::Bytes(vec![7; 100])]).unwrap();
+        let mut reader = RecordReader::with_max_len(Cursor::new(&record), 50);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(StorageError::RecordTooLong {
+                len: 106,
+                max_len: 50
+            }))
+        ));
+        let mut reader = RecordReader::with_max_len(Cursor::new(&record), 106);
+        assert_eq!(
+            reader.next().unwrap().unwrap(),
+            vec![DataField::Bytes(vec![7; 100])]
+        );
+    }
+
+    #[test]
+    fn test_frame_record_rejects_counts_that_look_packed() {
+        assert!(matches!(
+            frame_record(&vec![DataField::Null; 40000]),
+            Err(StorageError::Page(PageError::TooManyFields {
+                count: 40000
+            }))
+        ));
+        let row = vec![DataField::Null; crate::page::MAX_FIELDS];
+        let record = frame_record(&row).unwrap();
+        let mut reader = RecordReader::with_max_len(Cursor::new(&record), record.len());
+        assert_eq!(reader.next().unwrap().unwrap(), row);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::page::{DataField, PageError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Text,
+    Integer,
+    Int32,
+    Int64,
+    Float64,
+    Bool,
+    Bytes,
+    LargeText,
+    UInt32,
+    Timestamp,
+    Uuid,
+    VarInt,
+    Decimal,
+    Char,
+    Array,
+    CheckedBytes,
+    Json,
+    Int16,
+}
+
+impl DataField {
+    /// Returns `None` for `Null`, which is accepted in any column, and for an
+    /// unresolved `Overflow` reference.
+    pub fn field_type(&self) -> Option<FieldType> {
+        match self {
+            DataField::Text(_) => Some(FieldType::Text),
+            DataField::Integer(_) => Some(FieldType::Integer),
+            DataField::Int32(_) => Some(FieldType::Int32),
+            DataField::Int64(_) => Some(FieldType::Int64),
+            DataField::Float64(_) => Some(FieldType::Float64),
+            DataField::Null | DataField::Overflow { .. } => None,
+            DataField::Bool(_) => Some(FieldType::Bool),
+            DataField::Bytes(_) => Some(FieldType::Bytes),
+            DataField::LargeText(_) => Some(FieldType::LargeText),
+            DataField::UInt32(_) => Some(FieldType::UInt32),
+            DataField::Timestamp(_) => Some(FieldType::Timestamp),
+            DataField::Uuid(_) => Some(FieldType::Uuid),
+            DataField::VarInt(_) => Some(FieldType::VarInt),
+            DataField::Decimal { .. } => Some(FieldType::Decimal),
+            DataField::Char(_) => Some(FieldType::Char),
+            DataField::Array(_) => Some(FieldType::Array),
+            DataField::CheckedBytes(_) => Some(FieldType::CheckedBytes),
+            DataField::Json(_) => Some(FieldType::Json),
+            DataField::Int16(_) => Some(FieldType::Int16),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    columns: Vec<(String, FieldType)>,
+}
+
+impl Schema {
+    pub fn new(columns: Vec<(String, FieldType)>) -> Self {
+        Self { columns }
+    }
+
+    pub fn columns(&self) -> &[(String, FieldType)] {
+        &self.columns
+    }
+
+    pub fn validate(&self, values: &[DataField]) -> Result<(), PageError> {
+        if values.len() != self.columns.len() {
+            return Err(PageError::ColumnCountMismatch {
+                expected: self.columns.len(),
+                found: values.len(),
+            });
+        }
+        for ((column, field_type), value) in self.columns.iter().zip(values) {
+            if let Some(value_type) = value.field_type() {
+                if value_type != *field_type {
+                    return Err(PageError::TypeMismatch {
+                        column: column.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page::Page;
+
+    fn users_schema() -> Schema {
+        Schema::new(vec![
+            ("id".to_string(), FieldType::Integer),
+            ("name".to_string(), FieldType::Text),
+        ])
+    }
+
+    #[test]
+    fn test_write_row_matching_schema() {
+        let page = Page::new(0, 8192, None);
+        let schema = users_schema();
+        let values = vec![DataField::Integer(1), DataField::Text("ada".to_string())];
+        page.write_row(&schema, &values).unwrap();
+        page.write_row(&schema, &[DataField::Integer(2), DataField::Null])
+            .unwrap();
+        assert_eq!(
+            page.read().unwrap(),
+            vec![values, vec![DataField::Integer(2), DataField::Null]]
+        );
+    }
+
+    #[test]
+    fn test_write_row_type_mismatch() {
+        let page = Page::new(0, 8192, None);
+        let schema = users_schema();
+        assert_eq!(
+            page.write_row(
+                &schema,
+                &[DataField::Text("1".to_string()), DataField::Integer(1)]
+            ),
+            Err(PageError::TypeMismatch {
+                column: "id".to_string()
+            })
+        );
+        assert_eq!(
+            page.write_row(&schema, &[DataField::Integer(1)]),
+            Err(PageError::ColumnCountMismatch {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(page.count(), 0);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::checksum::crc32;
+use crate::page::{DataField, Endianness, Page};
+use crate::Result;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalEntry {
+    pub page_id: u16,
+    pub slot: u16,
+    pub data_fields: Vec<DataField>,
+}
+
+/// Append-only log of rows that are about to be written into a page.
+///
+/// Each record is `[len: u32][page_id: u16][slot: u16][row][crc32: u32]`, where
+/// `len` covers everything after itself and the checksum covers the page id,
+/// slot and row. A torn record at the end of the log is ignored on replay.
+pub struct WriteAheadLog {
+    file: File,
+}
+
+impl WriteAheadLog {
+    pub fn path_for(file_path: &str) -> String {
+        format!("{}.wal", file_path)
+    }
+
+    pub fn open(wal_path: &str) -> Result<WriteAheadLog> {
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(wal_path)?;
+        Ok(WriteAheadLog { file })
+    }
+
+    /// Appends a record and syncs it to disk before returning.
+    pub fn append(&mut self, page_id: u16, slot: u16, data_fields: &[DataField]) -> Result<()> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&page_id.to_le_bytes());
+        body.extend_from_slice(&slot.to_le_bytes());
+        body.extend_from_slice(&Page::encode_row(data_fields, Endianness::Little)?);
+        let mut record = Vec::with_capacity(body.len() + 8);
+        record.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        record.extend_from_slice(&body);
+        record.extend_from_slice(&crc32(&body).to_le_bytes());
+        self.file.write_all(&record)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    pub fn replay(&mut self) -> Result<Vec<WalEntry>> {
+        let mut bytes = Vec::new();
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_end(&mut bytes)?;
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset + 4 <= bytes.len() {
+            let len = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+            offset += 4;
+            if len < 8 || offset + len > bytes.len() {
+                break;
+            }
+            let body = &bytes[offset..offset + len - 4];
+            let stored =
+                u32::from_le_bytes(bytes[offset + len - 4..offset + len].try_into().unwrap());
+            if stored != crc32(body) {
+                break;
+            }
+            offset += len;
+            entries.push(WalEntry {
+                page_id: u16::from_le_bytes(body[0..2].try_into().unwrap()),
+                slot: u16::from_le_bytes(body[2..4].try_into().unwrap()),
+                data_fields: Page::decode_row(&body[4..], 0, 0, Endianness::Little)?,
+            });
+        }
+        Ok(entries)
+    }
+
+    pub fn truncate(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_all()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_wal_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "simplified_storage_{}_{}.wal",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_append_replay_truncate() {
+        let wal_path = temp_wal_path("append_replay_truncate");
+        let mut wal = WriteAheadLog::open(&wal_path).unwrap();
+        let row = vec![DataField::Integer(7), DataField::Text("logged".to_string())];
+        wal.append(1, 3, &row).unwrap();
+        wal.append(2, 0, &[DataField::Null]).unwrap();
+        let entries = WriteAheadLog::open(&wal_path).unwrap().replay().unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                WalEntry {
+                    page_id: 1,
+                    slot: 3,
+                    data_fields: row
+                },
+                WalEntry {
+                    page_id: 2,
+                    slot: 0,
+                    data_fields: vec![DataField::Null]
+                }
+            ]
+        );
+        wal.truncate().unwrap();
+        assert!(wal.replay().unwrap().is_empty());
+        std::fs::remove_file(&wal_path).unwrap();
+    }
+
+    #[test]
+    fn test_replay_ignores_torn_record() {
+        let wal_path = temp_wal_path("replay_ignores_torn_record");
+        let mut wal = WriteAheadLog::open(&wal_path).unwrap();
+        wal.append(0, 0, &[DataField::Integer(1)]).unwrap();
+        wal.append(0, 1, &[DataField::Integer(2)]).unwrap();
+        let len = std::fs::metadata(&wal_path).unwrap().len();
+        wal.file.set_len(len - 3).unwrap();
+        assert_eq!(wal.replay().unwrap().len(), 1);
+        std::fs::remove_file(&wal_path).unwrap();
+    }
+
+    #[test]
+    fn test_replay_reports_undecodable_row_as_page_error() {
+        let wal_path = temp_wal_path("replay_reports_undecodable_row");
+        // A checksummed record whose row claims one field of an unknown type.
+        let body = [0, 0, 0, 0, 1, 0, 0xFF, 0xFF];
+        let mut record = (body.len() as u32 + 4).to_le_bytes().to_vec();
+        record.extend_from_slice(&body);
+        record.extend_from_slice(&crc32(&body).to_le_bytes());
+        std::fs::write(&wal_path, &record).unwrap();
+        assert!(matches!(
+            WriteAheadLog::open(&wal_path).unwrap().replay(),
+            Err(crate::StorageError::Page(_))
+        ));
+        std::fs::remove_file(&wal_path).unwrap();
+    }
+}